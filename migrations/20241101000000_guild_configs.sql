@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS guild_configs (
+    guild_id INTEGER PRIMARY KEY,
+    category_id INTEGER,
+    log_channel_id INTEGER
+);
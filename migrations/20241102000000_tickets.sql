@@ -0,0 +1,22 @@
+CREATE TABLE IF NOT EXISTS tickets (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id INTEGER NOT NULL,
+    channel_id INTEGER NOT NULL,
+    opener_id INTEGER NOT NULL,
+    created_at INTEGER NOT NULL,
+    closed_at INTEGER,
+    closed_by INTEGER,
+    close_reason TEXT,
+    status TEXT NOT NULL DEFAULT 'open'
+);
+
+CREATE INDEX IF NOT EXISTS tickets_channel_id ON tickets (channel_id);
+CREATE INDEX IF NOT EXISTS tickets_guild_status ON tickets (guild_id, status);
+
+CREATE TABLE IF NOT EXISTS ticket_participants (
+    ticket_id INTEGER NOT NULL REFERENCES tickets (id),
+    user_id INTEGER NOT NULL,
+    added_by INTEGER NOT NULL,
+    added_at INTEGER NOT NULL,
+    PRIMARY KEY (ticket_id, user_id)
+);
@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS ticket_transcripts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    ticket_id INTEGER NOT NULL REFERENCES tickets (id),
+    created_at INTEGER NOT NULL,
+    message_count INTEGER NOT NULL,
+    html TEXT NOT NULL,
+    text TEXT NOT NULL
+);
+
+ALTER TABLE tickets ADD COLUMN transcript_id INTEGER REFERENCES ticket_transcripts (id);
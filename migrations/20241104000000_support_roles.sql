@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS support_roles (
+    guild_id INTEGER NOT NULL,
+    role_id INTEGER NOT NULL,
+    PRIMARY KEY (guild_id, role_id)
+);
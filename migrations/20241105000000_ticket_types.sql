@@ -0,0 +1,18 @@
+CREATE TABLE IF NOT EXISTS ticket_types (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    emoji TEXT,
+    category_id INTEGER,
+    welcome_message TEXT,
+    channel_name_template TEXT,
+    UNIQUE (guild_id, name)
+);
+
+CREATE TABLE IF NOT EXISTS ticket_type_roles (
+    type_id INTEGER NOT NULL REFERENCES ticket_types (id) ON DELETE CASCADE,
+    role_id INTEGER NOT NULL,
+    PRIMARY KEY (type_id, role_id)
+);
+
+ALTER TABLE tickets ADD COLUMN type_id INTEGER REFERENCES ticket_types (id) ON DELETE SET NULL;
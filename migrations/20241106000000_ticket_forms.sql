@@ -0,0 +1,17 @@
+CREATE TABLE IF NOT EXISTS ticket_questions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    type_id INTEGER NOT NULL REFERENCES ticket_types (id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    label TEXT NOT NULL,
+    style TEXT NOT NULL DEFAULT 'short',
+    required BOOLEAN NOT NULL DEFAULT TRUE,
+    placeholder TEXT
+);
+
+CREATE TABLE IF NOT EXISTS ticket_answers (
+    ticket_id INTEGER NOT NULL REFERENCES tickets (id),
+    position INTEGER NOT NULL,
+    question TEXT NOT NULL,
+    answer TEXT NOT NULL,
+    PRIMARY KEY (ticket_id, position)
+);
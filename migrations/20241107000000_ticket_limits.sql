@@ -0,0 +1,6 @@
+ALTER TABLE guild_configs ADD COLUMN max_open_tickets INTEGER;
+ALTER TABLE guild_configs ADD COLUMN ticket_cooldown_secs INTEGER;
+
+ALTER TABLE ticket_types ADD COLUMN max_open_tickets INTEGER;
+
+CREATE INDEX IF NOT EXISTS tickets_opener ON tickets (guild_id, opener_id, status);
@@ -0,0 +1,4 @@
+ALTER TABLE guild_configs ADD COLUMN ticket_counter INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE guild_configs ADD COLUMN channel_name_template TEXT;
+
+ALTER TABLE tickets ADD COLUMN number INTEGER;
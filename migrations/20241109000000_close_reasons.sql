@@ -0,0 +1 @@
+ALTER TABLE ticket_types ADD COLUMN require_close_reason BOOLEAN NOT NULL DEFAULT FALSE;
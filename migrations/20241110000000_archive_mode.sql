@@ -0,0 +1,8 @@
+ALTER TABLE guild_configs ADD COLUMN close_mode TEXT;
+ALTER TABLE guild_configs ADD COLUMN archive_category_id INTEGER;
+ALTER TABLE guild_configs ADD COLUMN archive_retention_hours INTEGER;
+
+ALTER TABLE ticket_types ADD COLUMN close_mode TEXT;
+
+ALTER TABLE tickets ADD COLUMN archived_from_category_id INTEGER;
+ALTER TABLE tickets ADD COLUMN archived_from_name TEXT;
@@ -0,0 +1,4 @@
+ALTER TABLE tickets ADD COLUMN claimed_by INTEGER;
+ALTER TABLE tickets ADD COLUMN claimed_at INTEGER;
+
+ALTER TABLE guild_configs ADD COLUMN claim_read_only BOOLEAN NOT NULL DEFAULT FALSE;
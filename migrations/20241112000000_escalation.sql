@@ -0,0 +1,30 @@
+CREATE TABLE IF NOT EXISTS escalation_tiers (
+    guild_id INTEGER NOT NULL,
+    level INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    category_id INTEGER,
+    role_id INTEGER NOT NULL,
+    PRIMARY KEY (guild_id, level)
+);
+
+ALTER TABLE tickets ADD COLUMN escalation_level INTEGER NOT NULL DEFAULT 0;
+
+CREATE TABLE IF NOT EXISTS ticket_escalations (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    ticket_id INTEGER NOT NULL REFERENCES tickets (id),
+    level INTEGER NOT NULL,
+    escalated_by INTEGER NOT NULL,
+    reason TEXT,
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS ticket_escalations_ticket ON ticket_escalations (ticket_id);
+
+CREATE TABLE IF NOT EXISTS ticket_transfers (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    ticket_id INTEGER NOT NULL REFERENCES tickets (id),
+    from_id INTEGER,
+    to_id INTEGER NOT NULL,
+    transferred_by INTEGER NOT NULL,
+    created_at INTEGER NOT NULL
+);
@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS scheduled_jobs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    ticket_id INTEGER NOT NULL REFERENCES tickets (id) ON DELETE CASCADE,
+    kind TEXT NOT NULL,
+    run_at INTEGER NOT NULL,
+    UNIQUE (ticket_id, kind)
+);
+
+CREATE INDEX IF NOT EXISTS scheduled_jobs_run_at ON scheduled_jobs (run_at);
+
+ALTER TABLE tickets ADD COLUMN last_activity_at INTEGER;
+
+ALTER TABLE guild_configs ADD COLUMN idle_close_hours INTEGER;
+ALTER TABLE guild_configs ADD COLUMN idle_grace_hours INTEGER;
@@ -0,0 +1,4 @@
+ALTER TABLE scheduled_jobs ADD COLUMN actor_id INTEGER;
+ALTER TABLE scheduled_jobs ADD COLUMN reason TEXT;
+
+ALTER TABLE guild_configs ADD COLUMN close_delay_secs INTEGER;
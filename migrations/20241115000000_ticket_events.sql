@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS ticket_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    ticket_id INTEGER NOT NULL REFERENCES tickets (id),
+    kind TEXT NOT NULL,
+    actor_id INTEGER NOT NULL,
+    target_id INTEGER,
+    payload TEXT NOT NULL DEFAULT '{}',
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS ticket_events_ticket ON ticket_events (ticket_id, id);
+CREATE INDEX IF NOT EXISTS ticket_events_actor ON ticket_events (actor_id);
+CREATE INDEX IF NOT EXISTS ticket_events_target ON ticket_events (target_id);
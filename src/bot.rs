@@ -1,141 +1,192 @@
-use crate::{commands::*, config::Config};
-use serenity::{all::*, async_trait, model::gateway::Ready, prelude::SerenityError};
+use crate::{
+    commands::InteractionContext,
+    config::Config,
+    error::TicketError,
+    permissions::{authorize, PermissionLevel},
+    response::{error_embed, respond_deferred},
+    router::{self, CommandScope, CustomId},
+    scheduler,
+};
+use serenity::{
+    all::*, async_trait, http::StatusCode, model::gateway::Ready, prelude::SerenityError,
+};
 use std::sync::Arc;
+use tracing::{error, field, info, info_span, warn, Instrument, Span};
 
 struct Handler {
     config: Arc<Config>,
 }
 
-#[async_trait]
-impl EventHandler for Handler {
-    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+impl Handler {
+    async fn check_permission(
+        &self,
+        interaction: &impl InteractionContext,
+        required: PermissionLevel,
+    ) -> bool {
+        match authorize(interaction, required, &self.config).await {
+            Ok(allowed) => allowed,
+            Err(why) => {
+                error!(error = %why, "Error checking permissions");
+                false
+            }
+        }
+    }
+
+    /// Opens the span everything logged while handling `interaction` is
+    /// recorded in, carrying the guild, member, command or component and, when
+    /// it happens inside one, the ticket.
+    async fn interaction_span(&self, interaction: &Interaction) -> Span {
+        let (span, channel_id) = match interaction {
+            Interaction::Command(command) => (
+                info_span!(
+                    "command",
+                    guild_id = command.guild_id.map(|id| id.get()),
+                    user_id = command.user.id.get(),
+                    command = %command.data.name,
+                    ticket_id = field::Empty,
+                ),
+                command.channel_id,
+            ),
+            Interaction::Component(component) => (
+                info_span!(
+                    "component",
+                    guild_id = component.guild_id.map(|id| id.get()),
+                    user_id = component.user.id.get(),
+                    custom_id = %component.data.custom_id,
+                    ticket_id = field::Empty,
+                ),
+                component.channel_id,
+            ),
+            Interaction::Modal(modal) => (
+                info_span!(
+                    "modal",
+                    guild_id = modal.guild_id.map(|id| id.get()),
+                    user_id = modal.user.id.get(),
+                    custom_id = %modal.data.custom_id,
+                    ticket_id = field::Empty,
+                ),
+                modal.channel_id,
+            ),
+            _ => return Span::none(),
+        };
+
+        if let Ok(Some(ticket)) = self.config.tickets.find_by_channel(channel_id).await {
+            span.record("ticket_id", ticket.id);
+        }
+        span
+    }
+
+    async fn handle_interaction(&self, ctx: Context, interaction: Interaction) {
         match interaction {
             Interaction::Command(command) => {
-                let content = match command.data.name.as_str() {
-                    "init" => init(&ctx, &command, &self.config).await,
-                    "close" => close(&ctx, &command, &self.config)
-                        .await
-                        .unwrap_or_else(|e| format!("Error: {}", e)),
-                    "adduser" => add_user(&ctx, &command, &self.config)
-                        .await
-                        .unwrap_or_else(|e| format!("Error: {}", e)),
-                    "removeuser" => remove_user(&ctx, &command, &self.config)
-                        .await
-                        .unwrap_or_else(|e| format!("Error: {}", e)),
-                    "setcategory" => set_category(&self.config, &command).await,
-                    "setlogchannel" => set_log_channel(&self.config, &command).await,
-                    _ => "Not implemented".to_string(),
+                let Some(slash) = router::find_command(&command.data.name) else {
+                    return;
                 };
-
-                if let Err(why) = command
-                    .create_response(
-                        &ctx.http,
-                        CreateInteractionResponse::Message(
-                            CreateInteractionResponseMessage::new().content(content),
-                        ),
-                    )
-                    .await
-                {
-                    println!("Cannot respond to slash command: {}", why);
+                let required = slash.required_permission(&command);
+                if !self.check_permission(&command, required).await {
+                    if let Err(why) = command
+                        .create_response(&ctx.http, permission_denied())
+                        .await
+                    {
+                        error!(error = %why, "Cannot respond to slash command");
+                    }
+                    return;
                 }
+
+                respond_deferred(
+                    &ctx,
+                    &command,
+                    slash.is_ephemeral(&command),
+                    slash.execute(&ctx, &command, &self.config),
+                )
+                .await;
             }
             Interaction::Component(component) => {
-                if component.data.custom_id == "open_ticket" {
-                    if let Some(guild_id) = component.guild_id {
-                        match guild_id.to_partial_guild(&ctx.http).await {
-                            Ok(guild) => {
-                                match create_ticket(&ctx, &component.user, &guild, &self.config)
-                                    .await
-                                {
-                                    Ok(guild_channel) => {
-                                        if let Err(why) = component
-                                            .create_response(
-                                                &ctx.http,
-                                                CreateInteractionResponse::Message(
-                                                    CreateInteractionResponseMessage::new()
-                                                        .content(format!(
-                                                            "Ticket created: {}",
-                                                            guild_channel.mention()
-                                                        ))
-                                                        .ephemeral(true),
-                                                ),
-                                            )
-                                            .await
-                                        {
-                                            println!("Error creating ticket: {}", why);
-                                        }
-                                    }
-                                    Err(why) => println!("Error creating ticket: {}", why),
-                                }
-                            }
-                            Err(why) => println!("Error fetching guild: {}", why),
-                        }
-                    }
-                } else if component.data.custom_id == "close_ticket" {
-                    if let Err(why) = close(&ctx, &component, &self.config).await {
-                        println!("Error closing ticket: {}", why);
+                let Some(id) = CustomId::parse(&component.data.custom_id) else {
+                    return;
+                };
+                if !self.check_permission(&component, id.permission()).await {
+                    if let Err(why) = component
+                        .create_response(&ctx.http, permission_denied())
+                        .await
+                    {
+                        error!(error = %why, "Cannot respond to component");
                     }
-                } else if component.data.custom_id == "cancel_close" {
-                    if let Err(why) = cancel_close(&ctx, &component).await {
-                        println!("Error cancelling close: {}", why);
+                    return;
+                }
+
+                router::run_component(&ctx, &component, id, &self.config).await;
+            }
+            Interaction::Modal(modal) => {
+                let Some(id) = CustomId::parse(&modal.data.custom_id) else {
+                    return;
+                };
+                if !self.check_permission(&modal, id.permission()).await {
+                    if let Err(why) = modal.create_response(&ctx.http, permission_denied()).await {
+                        error!(error = %why, "Cannot respond to modal");
                     }
+                    return;
                 }
+
+                router::run_modal(&ctx, &modal, id, &self.config).await;
             }
             _ => {}
         }
     }
+}
 
-    async fn ready(&self, ctx: Context, ready: Ready) {
-        println!("{} is connected!", ready.user.name);
-
-        match Command::get_global_commands(&ctx.http).await {
-            Ok(commands) => {
-                for command in commands {
-                    if let Err(why) = Command::delete_global_command(&ctx.http, command.id).await {
-                        println!(
-                            "Failed to delete global command {}: {:?}",
-                            command.name, why
-                        );
-                    } else {
-                        println!("Deleted global command: {}", command.name);
-                    }
-                }
-            }
-            Err(why) => println!("Failed to get global commands: {:?}", why),
+fn permission_denied() -> CreateInteractionResponse {
+    let error = TicketError::Permission("You do not have permission to do that.".into());
+    CreateInteractionResponse::Message(
+        CreateInteractionResponseMessage::new()
+            .embed(error_embed(&error))
+            .ephemeral(true),
+    )
+}
+
+#[async_trait]
+impl EventHandler for Handler {
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        let span = self.interaction_span(&interaction).await;
+        self.handle_interaction(ctx, interaction)
+            .instrument(span)
+            .await
+    }
+
+    async fn message(&self, _ctx: Context, message: Message) {
+        let Some(guild_id) = message.guild_id else {
+            return;
+        };
+        if message.author.bot {
+            return;
         }
 
-        let commands = vec![
-            CreateCommand::new("init").description("Initialize the ticket embed"),
-            CreateCommand::new("close").description("Close the current ticket"),
-            CreateCommand::new("adduser")
-                .description("Add a user to the ticket")
-                .add_option(
-                    CreateCommandOption::new(CommandOptionType::User, "user", "The user to add")
-                        .required(true),
-                ),
-            CreateCommand::new("removeuser")
-                .description("Remove a user from the ticket")
-                .add_option(
-                    CreateCommandOption::new(CommandOptionType::User, "user", "The user to remove")
-                        .required(true),
-                ),
-            CreateCommand::new("setcategory")
-                .description("Set the category ID for tickets")
-                .add_option(
-                    CreateCommandOption::new(CommandOptionType::String, "id", "The category ID")
-                        .required(true),
-                ),
-            CreateCommand::new("setlogchannel")
-                .description("Set the log channel ID")
-                .add_option(
-                    CreateCommandOption::new(CommandOptionType::String, "id", "The log channel ID")
-                        .required(true),
-                ),
-        ];
+        if let Err(why) =
+            scheduler::record_activity(guild_id, message.channel_id, &self.config).await
+        {
+            error!(error = %why, "Error recording ticket activity");
+        }
+    }
+
+    async fn channel_delete(
+        &self,
+        _ctx: Context,
+        channel: GuildChannel,
+        _messages: Option<Vec<Message>>,
+    ) {
+        if let Err(why) = self.config.tickets.mark_deleted(channel.id).await {
+            error!(error = %why, "Error updating deleted ticket channel");
+        }
+    }
 
-        match Command::set_global_commands(&ctx.http, commands).await {
-            Ok(_) => println!("Global slash commands registered successfully"),
-            Err(why) => println!("Failed to register global slash commands: {:?}", why),
+    async fn ready(&self, ctx: Context, ready: Ready) {
+        info!(user = %ready.user.name, "Connected");
+
+        if let Err(why) = register_commands(&ctx.http, &self.config).await {
+            error!(error = %why, "Failed to sync slash commands");
+        }
+        if let Err(why) = carry_over_legacy_config(&ctx.http, &self.config).await {
+            error!(error = %why, "Failed to carry over the old ticket settings");
         }
 
         ctx.set_presence(
@@ -148,81 +199,93 @@ impl EventHandler for Handler {
     }
 }
 
-pub async fn run(config: Arc<Config>) -> Result<(), SerenityError> {
-    let intents = GatewayIntents::non_privileged() | GatewayIntents::MESSAGE_CONTENT;
+/// Moves the category and log channel configured before settings were kept
+/// per guild to the guild those channels are in. Settings whose channels are
+/// all gone are dropped.
+async fn carry_over_legacy_config(http: &Http, config: &Config) -> Result<(), TicketError> {
+    let Some(legacy) = config.get_legacy_config().await? else {
+        return Ok(());
+    };
 
-    let mut client = Client::builder(&config.token, intents)
-        .event_handler(Handler {
-            config: Arc::clone(&config),
-        })
-        .await?;
-
-    if let Err(why) = client.start().await {
-        println!("An error occurred while running the client: {:?}", why);
+    let mut guild_id = None;
+    for channel_id in legacy.channel_ids() {
+        match channel_id.to_channel(http).await {
+            Ok(Channel::Guild(channel)) => {
+                guild_id = Some(channel.guild_id);
+                break;
+            }
+            Ok(_) => {}
+            Err(SerenityError::Http(why)) if why.status_code() == Some(StatusCode::NOT_FOUND) => {}
+            Err(why) => return Err(why.into()),
+        }
     }
 
+    config.carry_over_legacy_config(guild_id, &legacy).await?;
+    match guild_id {
+        Some(guild_id) => info!(%guild_id, "Carried over the old ticket settings"),
+        None => warn!("Dropped the old ticket settings, their channels no longer exist"),
+    }
     Ok(())
 }
 
-async fn cancel_close(
-    ctx: &Context,
-    component: &ComponentInteraction,
-) -> Result<(), SerenityError> {
-    let mut message = component.message.clone();
-    message
-        .edit(&ctx.http, EditMessage::new().components(vec![]))
-        .await?;
-
-    component
-        .create_response(
-            &ctx.http,
-            CreateInteractionResponse::Message(
-                CreateInteractionResponseMessage::new()
-                    .content("Ticket closure cancelled.")
-                    .ephemeral(true),
-            ),
-        )
-        .await?;
+fn command_scope(config: &Config) -> CommandScope {
+    match config.command_guild_id {
+        Some(guild_id) => CommandScope::Guild(guild_id),
+        None => CommandScope::Global,
+    }
+}
 
+async fn register_commands(http: &Http, config: &Config) -> Result<(), SerenityError> {
+    let scope = command_scope(config);
+    let summary = router::sync_commands(http, scope).await?;
+    info!(
+        ?scope,
+        created = summary.created,
+        updated = summary.updated,
+        deleted = summary.deleted,
+        unchanged = summary.unchanged,
+        "Slash commands synced"
+    );
     Ok(())
 }
 
-async fn set_category(config: &Arc<Config>, command: &CommandInteraction) -> String {
-    if let Some(id) = command
-        .data
-        .options
-        .first()
-        .and_then(|opt| opt.value.as_str())
-    {
-        if let Ok(category_id) = id.parse::<i64>() {
-            match config.set_category_id(category_id).await {
-                Ok(_) => format!("Category ID set to {}", category_id),
-                Err(_) => "Failed to set category ID".to_string(),
+/// Syncs the slash commands over HTTP only, without connecting to the
+/// gateway, for `--sync-commands`. With `clear_global`, syncing to a guild
+/// also deletes the application's global commands.
+pub async fn sync_commands(config: Arc<Config>, clear_global: bool) -> Result<(), SerenityError> {
+    let http = Http::new(&config.token);
+    let application = http.get_current_application_info().await?;
+    http.set_application_id(application.id);
+
+    register_commands(&http, &config).await?;
+    if clear_global {
+        match command_scope(&config) {
+            CommandScope::Guild(_) => {
+                let deleted = router::clear_global_commands(&http).await?;
+                info!(deleted, "Global slash commands cleared");
+            }
+            CommandScope::Global => {
+                warn!("Not clearing global commands without COMMAND_GUILD_ID set");
             }
-        } else {
-            "Invalid category ID provided".to_string()
         }
-    } else {
-        "No category ID provided".to_string()
     }
+    Ok(())
 }
 
-async fn set_log_channel(config: &Arc<Config>, command: &CommandInteraction) -> String {
-    if let Some(id) = command
-        .data
-        .options
-        .first()
-        .and_then(|opt| opt.value.as_str())
-    {
-        if let Ok(log_channel_id) = id.parse::<i64>() {
-            match config.set_log_channel_id(log_channel_id).await {
-                Ok(_) => format!("Log channel ID set to {}", log_channel_id),
-                Err(_) => "Failed to set log channel ID".to_string(),
-            }
-        } else {
-            "Invalid log channel ID provided".to_string()
-        }
-    } else {
-        "No log channel ID provided".to_string()
+pub async fn run(config: Arc<Config>) -> Result<(), SerenityError> {
+    let intents = GatewayIntents::non_privileged() | GatewayIntents::MESSAGE_CONTENT;
+
+    let mut client = Client::builder(&config.token, intents)
+        .event_handler(Handler {
+            config: Arc::clone(&config),
+        })
+        .await?;
+
+    scheduler::start(Arc::clone(&client.http), Arc::clone(&config));
+
+    if let Err(why) = client.start().await {
+        error!(error = %why, "Client stopped with an error");
     }
+
+    Ok(())
 }
@@ -1,79 +1,499 @@
-use crate::config::Config;
-use crate::logging::log_ticket_action;
+use crate::config::{
+    CloseMode, Config, EscalationTier, QuestionStyle, TicketType, TicketTypeFields,
+    DEFAULT_CLOSE_DELAY_SECS,
+};
+use crate::error::TicketError;
+use crate::logging::{log_ticket_action, log_ticket_details, log_transcript};
+use crate::permissions::{
+    member_level, missing_bot_permissions, require_bot_permissions, PermissionLevel,
+    ADMIN_PERMISSIONS, CATEGORY_PERMISSIONS, LOG_CHANNEL_PERMISSIONS, PANEL_PERMISSIONS,
+};
+use crate::router::CustomId;
+use crate::scheduler;
+use crate::tickets::{Ticket, TicketEvent, TicketEventKind, TicketStatus};
+use crate::transcript::{self, Transcript};
+use serde_json::json;
 use serenity::{
     all::*,
     builder::{CreateActionRow, CreateButton, CreateEmbed, CreateEmbedFooter, CreateMessage},
+    http::StatusCode,
     prelude::SerenityError,
 };
-use std::borrow::Cow;
-use std::error::Error;
-use std::fmt;
 use std::sync::Arc;
-use tokio::time::{sleep, Duration};
+use tracing::warn;
 
-#[derive(Debug)]
-struct TicketError(Cow<'static, str>);
+pub async fn init(
+    ctx: &Context,
+    command: &CommandInteraction,
+    config: &Arc<Config>,
+) -> Result<String, TicketError> {
+    let guild_id = guild_of(command)?;
 
-impl fmt::Display for TicketError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.0)
-    }
+    post_panel(ctx, guild_id, command.channel_id, config).await?;
+    Ok("Ticket system initialized successfully.".to_string())
 }
 
-impl Error for TicketError {}
+/// Posts the panel members open tickets from in `channel_id`, with a button
+/// or menu entry per ticket type.
+pub async fn post_panel(
+    ctx: &Context,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    config: &Arc<Config>,
+) -> Result<(), TicketError> {
+    let channel = guild_channel_of_kind(
+        ctx,
+        guild_id,
+        channel_id,
+        &[ChannelType::Text, ChannelType::News],
+        "a text channel",
+    )
+    .await?;
+    require_bot_permissions(ctx, &channel, PANEL_PERMISSIONS).await?;
+    let ticket_types = config.get_ticket_types(guild_id).await?;
 
-impl From<TicketError> for SerenityError {
-    fn from(error: TicketError) -> Self {
-        SerenityError::Other(Box::leak(error.0.into_owned().into_boxed_str()))
-    }
-}
+    let mut embed = CreateEmbed::new().title("Support Ticket").color(0x00ff00);
 
-pub async fn init(ctx: &Context, command: &CommandInteraction, config: &Arc<Config>) -> String {
-    let category_id = config.get_category_id().await.unwrap_or(None);
+    let components = if ticket_types.is_empty() {
+        embed = embed.description("Click the button below to open a new support ticket.");
 
-    let embed = CreateEmbed::new()
-        .title("Support Ticket")
-        .description("Click the button below to open a new support ticket.")
-        .color(0x00ff00)
-        .footer(CreateEmbedFooter::new(format!(
-            "Ticket Category ID: {}",
-            category_id.unwrap_or(0)
-        )));
+        let button = CreateButton::new(CustomId::OpenTicket { type_id: None }.to_string())
+            .label("Open Ticket")
+            .style(ButtonStyle::Primary);
 
-    let button = CreateButton::new("open_ticket")
-        .label("Open Ticket")
-        .style(ButtonStyle::Primary);
+        vec![CreateActionRow::Buttons(vec![button])]
+    } else if ticket_types.len() <= 5 {
+        embed = embed.description("Click the button that fits your issue to open a new ticket.");
 
-    let action_row = CreateActionRow::Buttons(vec![button]);
+        let buttons = ticket_types
+            .iter()
+            .map(|ticket_type| {
+                let button = CreateButton::new(
+                    CustomId::OpenTicket {
+                        type_id: Some(ticket_type.id),
+                    }
+                    .to_string(),
+                )
+                .label(&ticket_type.name)
+                .style(ButtonStyle::Primary);
+                match parse_emoji(ticket_type) {
+                    Some(emoji) => button.emoji(emoji),
+                    None => button,
+                }
+            })
+            .collect();
+
+        vec![CreateActionRow::Buttons(buttons)]
+    } else {
+        embed = embed.description("Pick the option that fits your issue to open a new ticket.");
+
+        let options = ticket_types
+            .iter()
+            .take(25)
+            .map(|ticket_type| {
+                let option =
+                    CreateSelectMenuOption::new(&ticket_type.name, ticket_type.id.to_string());
+                match parse_emoji(ticket_type) {
+                    Some(emoji) => option.emoji(emoji),
+                    None => option,
+                }
+            })
+            .collect();
+
+        let menu = CreateSelectMenu::new(
+            CustomId::OpenTicket { type_id: None }.to_string(),
+            CreateSelectMenuKind::String { options },
+        )
+        .placeholder("Open a ticket");
+
+        vec![CreateActionRow::SelectMenu(menu)]
+    };
 
-    if let Err(why) = command
-        .channel_id
+    channel
         .send_message(
             &ctx.http,
-            CreateMessage::new()
-                .embed(embed)
-                .components(vec![action_row]),
+            CreateMessage::new().embed(embed).components(components),
         )
-        .await
+        .await?;
+    Ok(())
+}
+
+fn parse_emoji(ticket_type: &TicketType) -> Option<ReactionType> {
+    ticket_type.emoji.as_deref()?.parse().ok()
+}
+
+/// Overwrite that lets a single member take part in a ticket.
+fn member_overwrite(user_id: UserId) -> PermissionOverwrite {
+    PermissionOverwrite {
+        allow: Permissions::VIEW_CHANNEL
+            | Permissions::SEND_MESSAGES
+            | Permissions::READ_MESSAGE_HISTORY,
+        deny: Permissions::empty(),
+        kind: PermissionOverwriteType::Member(user_id),
+    }
+}
+
+/// Replaces the overwrites in `existing` that target the same member or role
+/// as one of `updates` and adds the rest of `updates`.
+fn merge_overwrites(
+    existing: &[PermissionOverwrite],
+    updates: Vec<PermissionOverwrite>,
+) -> Vec<PermissionOverwrite> {
+    let mut merged = existing
+        .iter()
+        .filter(|overwrite| !updates.iter().any(|update| update.kind == overwrite.kind))
+        .cloned()
+        .collect::<Vec<_>>();
+    merged.extend(updates);
+    merged
+}
+
+/// Overwrite that lets a staff role work in a ticket.
+fn role_overwrite(role_id: RoleId) -> PermissionOverwrite {
+    PermissionOverwrite {
+        allow: Permissions::VIEW_CHANNEL
+            | Permissions::SEND_MESSAGES
+            | Permissions::READ_MESSAGE_HISTORY,
+        deny: Permissions::empty(),
+        kind: PermissionOverwriteType::Role(role_id),
+    }
+}
+
+/// Overwrites that let the staff roles of a ticket work in it. Applied to new
+/// ticket channels and re-applied when a ticket is reopened.
+pub async fn support_role_overwrites(
+    guild_id: GuildId,
+    type_id: Option<i64>,
+    config: &Arc<Config>,
+) -> Result<Vec<PermissionOverwrite>, TicketError> {
+    let roles = config.get_staff_roles(guild_id, type_id).await?;
+
+    Ok(roles.into_iter().map(role_overwrite).collect())
+}
+
+/// The staff overwrites `ticket` currently should have: those of its
+/// escalation tier once escalated, the usual staff roles otherwise.
+async fn ticket_staff_overwrites(
+    guild_id: GuildId,
+    ticket: &Ticket,
+    config: &Arc<Config>,
+) -> Result<Vec<PermissionOverwrite>, TicketError> {
+    match config
+        .get_escalation_tier(guild_id, ticket.escalation_level)
+        .await?
     {
-        format!("Failed to send ticket embed: {}", why)
+        Some(tier) => Ok(vec![role_overwrite(tier.role_id())]),
+        None => support_role_overwrites(guild_id, ticket.type_id, config).await,
+    }
+}
+
+/// Channel name used when neither the ticket type nor the guild sets one.
+pub const DEFAULT_CHANNEL_NAME_TEMPLATE: &str = "ticket-{number}";
+
+/// Fills in the `{number}`, `{user}`, `{userid}` and `{type}` placeholders of a
+/// channel name template and makes the result a valid channel name.
+fn render_channel_name(
+    template: &str,
+    number: i64,
+    user: &User,
+    ticket_type: Option<&TicketType>,
+) -> String {
+    let rendered = template
+        .replace("{number}", &format!("{:04}", number))
+        .replace("{userid}", &user.id.to_string())
+        .replace("{user}", &user.name)
+        .replace(
+            "{type}",
+            ticket_type
+                .map(|ticket_type| ticket_type.name.as_str())
+                .unwrap_or("ticket"),
+        );
+
+    let name = sanitize_channel_name(&rendered);
+    if name.is_empty() {
+        format!("ticket-{:04}", number)
+    } else {
+        name
+    }
+}
+
+/// Lowercases `name`, turns whitespace into dashes, drops characters Discord
+/// does not allow in text channel names and caps it at 100 characters.
+pub fn sanitize_channel_name(name: &str) -> String {
+    let mut sanitized = String::with_capacity(name.len());
+    for c in name.chars().flat_map(char::to_lowercase) {
+        let c = if c.is_whitespace() { '-' } else { c };
+        if !(c.is_alphanumeric() || c == '-' || c == '_') {
+            continue;
+        }
+        if c == '-' && (sanitized.is_empty() || sanitized.ends_with('-')) {
+            continue;
+        }
+        sanitized.push(c);
+    }
+
+    let capped = sanitized.chars().take(100).collect::<String>();
+    capped.trim_end_matches('-').to_string()
+}
+
+/// Looks up the ticket type a panel click asked for. The type comes from the
+/// button's custom id or from the selected menu entry.
+async fn requested_ticket_type(
+    component: &ComponentInteraction,
+    type_id: Option<i64>,
+    config: &Arc<Config>,
+) -> Result<Option<TicketType>, TicketError> {
+    let guild_id = guild_of(component)?;
+
+    let type_id = match &component.data.kind {
+        ComponentInteractionDataKind::StringSelect { values } => {
+            values.first().and_then(|value| value.parse::<i64>().ok())
+        }
+        _ => type_id,
+    };
+
+    match type_id {
+        Some(type_id) => Ok(Some(
+            config
+                .get_ticket_type(guild_id, type_id)
+                .await?
+                .ok_or(TicketError::Validation(
+                    "This ticket type no longer exists.".into(),
+                ))?,
+        )),
+        None => Ok(None),
+    }
+}
+
+/// Why `user` cannot open a ticket of `ticket_type` right now, if anything.
+/// A type with its own limit only counts open tickets of that type; every
+/// other ticket counts all of the member's open tickets against the server
+/// limit.
+async fn ticket_limit_message(
+    guild_id: GuildId,
+    user: &User,
+    ticket_type: Option<&TicketType>,
+    config: &Arc<Config>,
+) -> Result<Option<String>, TicketError> {
+    let open = config
+        .tickets
+        .list_open_for_user(guild_id, user.id, None)
+        .await?;
+    let type_limit = ticket_type.and_then(|ticket_type| {
+        ticket_type
+            .max_open_tickets
+            .map(|max| (ticket_type.id, max))
+    });
+    let (counted, max_open) = match type_limit {
+        Some((type_id, max)) => (
+            open.iter()
+                .filter(|ticket| ticket.type_id == Some(type_id))
+                .collect::<Vec<_>>(),
+            max,
+        ),
+        None => (
+            open.iter().collect(),
+            config.get_max_open_tickets(guild_id).await?,
+        ),
+    };
+
+    let existing = if counted.len() as i64 >= max_open {
+        counted.first()
     } else {
-        "Ticket system initialized successfully.".to_string()
+        None
+    };
+    if let Some(ticket) = existing {
+        return Ok(Some(format!(
+            "You already have an open ticket: {}",
+            ticket.channel_id().mention()
+        )));
+    }
+
+    let cooldown = config.get_ticket_cooldown(guild_id).await?;
+    if cooldown > 0 {
+        if let Some(last) = config.tickets.last_opened_at(guild_id, user.id).await? {
+            let ready_at = last + cooldown;
+            if ready_at > Timestamp::now().unix_timestamp() {
+                return Ok(Some(format!(
+                    "You can open another ticket <t:{}:R>.",
+                    ready_at
+                )));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// Builds the intake form for the requested ticket type, or `None` if the type
+/// has no questions and the ticket can be opened straight away.
+pub async fn ticket_form(
+    component: &ComponentInteraction,
+    type_id: Option<i64>,
+    config: &Arc<Config>,
+) -> Result<Option<CreateModal>, TicketError> {
+    let Some(guild_id) = component.guild_id else {
+        return Ok(None);
+    };
+    let Some(ticket_type) = requested_ticket_type(component, type_id, config).await? else {
+        return Ok(None);
+    };
+    if ticket_limit_message(guild_id, &component.user, Some(&ticket_type), config)
+        .await?
+        .is_some()
+    {
+        return Ok(None);
+    }
+
+    let questions = config.get_ticket_questions(ticket_type.id).await?;
+    if questions.is_empty() {
+        return Ok(None);
+    }
+
+    let rows = questions
+        .iter()
+        .take(5)
+        .map(|question| {
+            let style = match question.style {
+                QuestionStyle::Short => InputTextStyle::Short,
+                QuestionStyle::Paragraph => InputTextStyle::Paragraph,
+            };
+            let mut input = CreateInputText::new(style, &question.label, question.id.to_string())
+                .required(question.required);
+            // Placeholders saved before the option had a length limit may be
+            // longer than Discord accepts.
+            if let Some(placeholder) = &question.placeholder {
+                input = input.placeholder(placeholder.chars().take(100).collect::<String>());
+            }
+            CreateActionRow::InputText(input)
+        })
+        .collect();
+
+    let title: String = ticket_type.name.chars().take(45).collect();
+    Ok(Some(
+        CreateModal::new(
+            CustomId::TicketForm {
+                type_id: ticket_type.id,
+            }
+            .to_string(),
+            title,
+        )
+        .components(rows),
+    ))
+}
+
+/// Handles a click on the ticket panel for ticket types without questions.
+pub async fn open_ticket(
+    ctx: &Context,
+    component: &ComponentInteraction,
+    type_id: Option<i64>,
+    config: &Arc<Config>,
+) -> Result<String, TicketError> {
+    let guild_id = guild_of(component)?;
+    let ticket_type = requested_ticket_type(component, type_id, config).await?;
+    open_within_limits(
+        ctx,
+        &component.user,
+        guild_id,
+        ticket_type.as_ref(),
+        &[],
+        config,
+    )
+    .await
+}
+
+/// Opens a ticket of type `type_id` from its submitted intake form.
+pub async fn submit_ticket_form(
+    ctx: &Context,
+    modal: &ModalInteraction,
+    type_id: i64,
+    config: &Arc<Config>,
+) -> Result<String, TicketError> {
+    let guild_id = guild_of(modal)?;
+    let ticket_type =
+        config
+            .get_ticket_type(guild_id, type_id)
+            .await?
+            .ok_or(TicketError::Validation(
+                "This ticket type no longer exists.".into(),
+            ))?;
+    let questions = config.get_ticket_questions(type_id).await?;
+
+    let answers = modal
+        .data
+        .components
+        .iter()
+        .flat_map(|row| &row.components)
+        .filter_map(|component| match component {
+            ActionRowComponent::InputText(input) => Some(input),
+            _ => None,
+        })
+        .filter_map(|input| {
+            let question = questions
+                .iter()
+                .find(|question| question.id.to_string() == input.custom_id)?;
+            let answer = input.value.as_deref().unwrap_or_default().trim();
+            (!answer.is_empty()).then(|| (question.label.clone(), answer.to_string()))
+        })
+        .collect::<Vec<_>>();
+
+    open_within_limits(
+        ctx,
+        &modal.user,
+        guild_id,
+        Some(&ticket_type),
+        &answers,
+        config,
+    )
+    .await
+}
+
+/// Opens a ticket for `user` unless one of the limits is reached. The limit
+/// check and the ticket creation happen under the member's opening lock.
+async fn open_within_limits(
+    ctx: &Context,
+    user: &User,
+    guild_id: GuildId,
+    ticket_type: Option<&TicketType>,
+    answers: &[(String, String)],
+    config: &Arc<Config>,
+) -> Result<String, TicketError> {
+    let _opening = config.tickets.lock_opening(guild_id, user.id).await;
+    if let Some(message) = ticket_limit_message(guild_id, user, ticket_type, config).await? {
+        return Ok(message);
     }
+
+    let guild = guild_id.to_partial_guild(&ctx.http).await?;
+    let guild_channel = create_ticket(ctx, user, &guild, ticket_type, answers, config).await?;
+    Ok(format!("Ticket created: {}", guild_channel.mention()))
 }
 
 pub async fn create_ticket(
     ctx: &Context,
     user: &User,
     guild: &PartialGuild,
+    ticket_type: Option<&TicketType>,
+    answers: &[(String, String)],
     config: &Arc<Config>,
-) -> Result<GuildChannel, SerenityError> {
-    let category_id = config
-        .get_category_id()
-        .await
-        .unwrap_or(None)
-        .map(|id| id as u64);
-    let channel_name = format!("ticket-{}", user.name.to_lowercase());
+) -> Result<GuildChannel, TicketError> {
+    let category_id = match ticket_type.and_then(TicketType::category_id) {
+        Some(id) => Some(id),
+        None => config
+            .get_category_id(guild.id)
+            .await
+            .unwrap_or(None)
+            .map(|id| ChannelId::new(id as u64)),
+    };
+    let template =
+        match ticket_type.and_then(|ticket_type| ticket_type.channel_name_template.clone()) {
+            Some(template) => template,
+            None => config
+                .get_channel_name_template(guild.id)
+                .await?
+                .unwrap_or_else(|| DEFAULT_CHANNEL_NAME_TEMPLATE.to_string()),
+        };
+    let number = config.next_ticket_number(guild.id).await?;
+    let channel_name = render_channel_name(&template, number, user, ticket_type);
 
     let everyone_role = guild
         .roles
@@ -82,46 +502,94 @@ pub async fn create_ticket(
         .unwrap()
         .id;
 
-    let channel_builder = CreateChannel::new(channel_name.clone())
+    let type_id = ticket_type.map(|ticket_type| ticket_type.id);
+    let mut permissions = vec![
+        member_overwrite(user.id),
+        PermissionOverwrite {
+            allow: Permissions::empty(),
+            deny: Permissions::VIEW_CHANNEL,
+            kind: PermissionOverwriteType::Role(everyone_role),
+        },
+    ];
+    permissions.extend(support_role_overwrites(guild.id, type_id, config).await?);
+
+    let mut channel_builder = CreateChannel::new(channel_name.clone())
         .kind(ChannelType::Text)
-        .category(ChannelId::new(category_id.unwrap_or(0)))
-        .permissions(vec![
-            PermissionOverwrite {
-                allow: Permissions::VIEW_CHANNEL
-                    | Permissions::SEND_MESSAGES
-                    | Permissions::READ_MESSAGE_HISTORY,
-                deny: Permissions::empty(),
-                kind: PermissionOverwriteType::Member(user.id),
-            },
-            PermissionOverwrite {
-                allow: Permissions::empty(),
-                deny: Permissions::VIEW_CHANNEL,
-                kind: PermissionOverwriteType::Role(everyone_role),
-            },
-        ]);
+        .permissions(permissions);
+    if let Some(category_id) = category_id {
+        channel_builder = channel_builder.category(category_id);
+    }
 
     let guild_channel = guild.create_channel(&ctx.http, channel_builder).await?;
+    let tracked = async {
+        let ticket = config
+            .tickets
+            .open(guild.id, guild_channel.id, user.id, type_id, number)
+            .await?;
+        if !answers.is_empty() {
+            config.tickets.save_answers(&ticket, answers).await?;
+        }
+        config
+            .tickets
+            .record_event(
+                &ticket,
+                TicketEventKind::Opened,
+                user.id,
+                None,
+                json!({
+                    "type": ticket_type.map(|ticket_type| &ticket_type.name),
+                    "number": number,
+                }),
+            )
+            .await?;
+        scheduler::record_activity(guild.id, guild_channel.id, config).await?;
+        Ok::<_, TicketError>(())
+    }
+    .await;
+    if let Err(e) = tracked {
+        // Nothing can close a channel the database does not know about, so
+        // do not leave it behind. Deleting it also marks a row that did get
+        // written as deleted.
+        if let Err(why) = guild_channel.delete(&ctx.http).await {
+            warn!(error = %why, channel_id = guild_channel.id.get(), "Failed to delete untracked ticket channel");
+        }
+        return Err(e);
+    }
 
-    let embed = CreateEmbed::new()
-        .title("New Ticket")
-        .description(
+    let description = ticket_type
+        .and_then(|ticket_type| ticket_type.welcome_message.as_deref())
+        .unwrap_or(
             "Please describe the reasoning for opening this ticket, include any information you \
             think may be relevant such as proof, other third parties and so on.\n\n\
-            Use `/adduser` if you want to add another user.\n\
+            Use `/ticket add` if you want to add another user.\n\
             Do not add them if they are the subject of a report, as they can close the ticket.\n\n\
-            Please close the ticket using `/close` when you feel that the issue is resolved.",
-        )
+            Please close the ticket using `/ticket close` when you feel that the issue is resolved.",
+        );
+
+    let mut embed = CreateEmbed::new()
+        .title(match ticket_type {
+            Some(ticket_type) => format!("New Ticket: {}", ticket_type.name),
+            None => "New Ticket".to_string(),
+        })
+        .description(description)
         .color(0x2b2d31)
         .footer(CreateEmbedFooter::new(format!(
             "Channel: #{}",
             channel_name
         )));
+    for (question, answer) in answers {
+        let answer: String = answer.chars().take(1024).collect();
+        embed = embed.field(question, answer, false);
+    }
 
-    let close_button = CreateButton::new("close_ticket")
+    let close_button = CreateButton::new(CustomId::CloseTicket.to_string())
         .label("Close Ticket")
         .style(ButtonStyle::Danger);
+    let claim_button = CreateButton::new(CustomId::ClaimTicket.to_string())
+        .label("Claim")
+        .style(ButtonStyle::Primary);
 
-    let action_row = CreateActionRow::Buttons(vec![close_button]);
+    let action_row = CreateActionRow::Buttons(vec![close_button, claim_button]);
 
     guild_channel
         .send_message(
@@ -136,29 +604,371 @@ pub async fn create_ticket(
         )
         .await?;
 
-    log_ticket_action(ctx, "Opened", user, &guild_channel, config).await?;
+    log_ticket_action(ctx, "Opened", user, &guild_channel, None, config).await?;
     Ok(guild_channel)
 }
 
+async fn close_reason_required(
+    guild_id: Option<GuildId>,
+    ticket: &Ticket,
+    config: &Arc<Config>,
+) -> Result<bool, TicketError> {
+    let (Some(guild_id), Some(type_id)) = (guild_id, ticket.type_id) else {
+        return Ok(false);
+    };
+    Ok(config
+        .get_ticket_type(guild_id, type_id)
+        .await?
+        .is_some_and(|ticket_type| ticket_type.require_close_reason))
+}
+
+/// Builds the modal the "Close Ticket" button opens to ask for a reason.
+pub async fn close_form(
+    component: &ComponentInteraction,
+    config: &Arc<Config>,
+) -> Result<CreateModal, TicketError> {
+    let ticket = ticket_member_in(component, config).await?;
+    let required = close_reason_required(component.guild_id, &ticket, config).await?;
+
+    let input = CreateInputText::new(InputTextStyle::Paragraph, "Reason", "reason")
+        .required(required)
+        .max_length(1000);
+    Ok(
+        CreateModal::new(CustomId::CloseReason.to_string(), "Close Ticket")
+            .components(vec![CreateActionRow::InputText(input)]),
+    )
+}
+
+/// Reads the reason out of a submitted `close_reason` modal.
+pub fn close_form_reason(modal: &ModalInteraction) -> Option<&str> {
+    modal
+        .data
+        .components
+        .iter()
+        .flat_map(|row| &row.components)
+        .find_map(|component| match component {
+            ActionRowComponent::InputText(input) if input.custom_id == "reason" => {
+                input.value.as_deref()
+            }
+            _ => None,
+        })
+        .map(str::trim)
+        .filter(|reason| !reason.is_empty())
+}
+
+/// Lets the opener know their ticket was closed and why. Failures are only
+/// logged since many members do not accept DMs.
+async fn notify_opener(
+    http: &impl CacheHttp,
+    ticket: &Ticket,
+    channel: &GuildChannel,
+    closed_by: &User,
+    reason: Option<&str>,
+) {
+    let guild_name = match channel.guild_id.to_partial_guild(http).await {
+        Ok(guild) => guild.name,
+        Err(_) => "the server".to_string(),
+    };
+    let embed = CreateEmbed::new()
+        .title("Ticket Closed")
+        .description(format!(
+            "Your ticket #{} in {} was closed by {}.",
+            channel.name, guild_name, closed_by.name
+        ))
+        .field("Reason", reason.unwrap_or("No reason given"), false)
+        .color(0xff0000)
+        .timestamp(Timestamp::now());
+
+    let result = match ticket.opener_id().create_dm_channel(http).await {
+        Ok(dm) => dm
+            .send_message(http, CreateMessage::new().embed(embed))
+            .await
+            .map(|_| ()),
+        Err(why) => Err(why),
+    };
+    if let Err(why) = result {
+        warn!(error = %why, "Could not DM ticket opener");
+    }
+}
+
+/// The close mode of `ticket`'s type, or the guild's when the type does not
+/// set one.
+async fn close_mode(
+    guild_id: GuildId,
+    ticket: &Ticket,
+    config: &Arc<Config>,
+) -> Result<CloseMode, TicketError> {
+    if let Some(type_id) = ticket.type_id {
+        if let Some(mode) = config
+            .get_ticket_type(guild_id, type_id)
+            .await?
+            .and_then(|ticket_type| ticket_type.close_mode)
+        {
+            return Ok(mode);
+        }
+    }
+    Ok(config.get_close_mode(guild_id).await?)
+}
+
+/// Hides a closed ticket from its opener and participants, renames it to
+/// `closed-…` and moves it into the archive category, leaving a button staff
+/// can use to reopen it.
+async fn archive_channel(
+    http: &impl CacheHttp,
+    channel: &GuildChannel,
+    ticket: &Ticket,
+    config: &Arc<Config>,
+) -> Result<(), TicketError> {
+    let mut members = config.tickets.list_participants(ticket).await?;
+    members.push(ticket.opener_id());
+
+    let permissions = channel
+        .permission_overwrites
+        .iter()
+        .filter(|overwrite| {
+            !matches!(overwrite.kind, PermissionOverwriteType::Member(id) if members.contains(&id))
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    let name = sanitize_channel_name(&format!("closed-{}", channel.name));
+
+    let mut edit = EditChannel::new().name(name).permissions(permissions);
+    if let Some(category_id) = config.get_archive_category_id(channel.guild_id).await? {
+        edit = edit.category(ChannelId::new(category_id as u64));
+    }
+    channel.id.edit(http, edit).await?;
+    // Only recorded once the channel is actually archived, as archive purging
+    // and retried closes go by it.
+    config
+        .tickets
+        .record_archive(ticket, channel.parent_id, &channel.name)
+        .await?;
+
+    let button = CreateButton::new(CustomId::ReopenTicket.to_string())
+        .label("Reopen")
+        .style(ButtonStyle::Success);
+    channel
+        .id
+        .send_message(
+            http,
+            CreateMessage::new()
+                .embed(
+                    CreateEmbed::new()
+                        .title("Ticket Archived")
+                        .description(
+                            "This ticket is closed. Staff can reopen it below or with `/ticket reopen`.",
+                        )
+                        .color(0x2b2d31),
+                )
+                .components(vec![CreateActionRow::Buttons(vec![button])]),
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Reopens an archived ticket: restores its name and category and gives the
+/// opener, the participants and the staff roles their access back.
+pub async fn reopen(
+    ctx: &Context,
+    interaction: &impl InteractionContext,
+    config: &Arc<Config>,
+) -> Result<String, TicketError> {
+    let guild_id = guild_of(interaction)?;
+    let Some(ticket) = config
+        .tickets
+        .find_by_channel(interaction.channel_id())
+        .await?
+    else {
+        return Err(TicketError::Validation(
+            "This channel is not a ticket.".into(),
+        ));
+    };
+    if ticket.status != TicketStatus::Closed {
+        return Err(TicketError::Validation(
+            "This ticket is not archived.".into(),
+        ));
+    }
+    let Channel::Guild(channel) = interaction.channel_id().to_channel(ctx).await? else {
+        return Err(TicketError::Validation(
+            "This ticket is not a server channel.".into(),
+        ));
+    };
+
+    let archived_from = config.tickets.archived_from(&ticket).await?;
+    let mut members = config.tickets.list_participants(&ticket).await?;
+    members.push(ticket.opener_id());
+    let staff = ticket_staff_overwrites(guild_id, &ticket, config).await?;
+
+    if !config.tickets.reopen(&ticket).await? {
+        return Ok("This ticket is already being reopened.".to_string());
+    }
+    config
+        .tickets
+        .record_event(
+            &ticket,
+            TicketEventKind::Reopened,
+            interaction.user().id,
+            None,
+            json!({}),
+        )
+        .await?;
+
+    let mut updates = members
+        .into_iter()
+        .map(member_overwrite)
+        .collect::<Vec<_>>();
+    updates.extend(staff);
+    let permissions = merge_overwrites(&channel.permission_overwrites, updates);
+
+    let mut edit = EditChannel::new()
+        .permissions(permissions)
+        .category(archived_from.category_id());
+    if let Some(name) = &archived_from.name {
+        edit = edit.name(name);
+    }
+    let channel = channel.id.edit(&ctx.http, edit).await?;
+    scheduler::record_activity(guild_id, channel.id, config).await?;
+
+    log_ticket_action(ctx, "Reopened", interaction.user(), &channel, None, config).await?;
+    Ok(format!(
+        "Ticket reopened by {}. {}",
+        interaction.user().mention(),
+        ticket.opener_id().mention()
+    ))
+}
+
+/// Longest delay `/ticket close` accepts.
+const MAX_CLOSE_DELAY_SECS: i64 = 7 * 24 * 3600;
+
+/// Parses durations like `90`, `30s`, `10m`, `2h` or `1d12h` into seconds.
+pub fn parse_duration(input: &str) -> Result<i64, TicketError> {
+    let invalid = || {
+        TicketError::Validation(
+            format!(
+                "\"{}\" is not a duration. Use something like 30s, 10m, 2h or 1d.",
+                input
+            )
+            .into(),
+        )
+    };
+
+    let input = input.trim();
+    if input.is_empty() {
+        return Err(invalid());
+    }
+    if let Ok(secs) = input.parse::<i64>() {
+        return if secs >= 0 { Ok(secs) } else { Err(invalid()) };
+    }
+
+    let mut total: i64 = 0;
+    let mut digits = String::new();
+    for c in input.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+        let unit = match c.to_ascii_lowercase() {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            _ => return Err(invalid()),
+        };
+        let amount = digits.parse::<i64>().map_err(|_| invalid())?;
+        total = amount
+            .checked_mul(unit)
+            .and_then(|secs| total.checked_add(secs))
+            .ok_or_else(invalid)?;
+        digits.clear();
+    }
+    if !digits.is_empty() {
+        return Err(invalid());
+    }
+    Ok(total)
+}
+
+/// Schedules the ticket to be closed after `delay` seconds, or the guild's
+/// close delay. The close is stored as a job so it survives restarts and can
+/// be cancelled with the button on the posted message.
 pub async fn close(
     ctx: &Context,
     interaction: &impl InteractionContext,
+    reason: Option<&str>,
+    delay: Option<i64>,
     config: &Arc<Config>,
-) -> Result<String, SerenityError> {
+) -> Result<String, TicketError> {
     let channel_id = interaction.channel_id();
+    let ticket = match config.tickets.find_by_channel(channel_id).await? {
+        Some(ticket) if ticket.status == TicketStatus::PendingClose => {
+            return Ok("This ticket is already being closed.".to_string());
+        }
+        _ => ticket_member_in(interaction, config).await?,
+    };
 
-    let embed = CreateEmbed::new()
+    if reason.is_none() && close_reason_required(interaction.guild_id(), &ticket, config).await? {
+        return Err(TicketError::Validation(
+            "A reason is required to close this ticket.".into(),
+        ));
+    }
+    if delay.is_some_and(|delay| delay > MAX_CLOSE_DELAY_SECS) {
+        return Err(TicketError::Validation(
+            "Tickets can be closed at most 7 days out.".into(),
+        ));
+    }
+    let delay = match (delay, interaction.guild_id()) {
+        (Some(delay), _) => delay,
+        (None, Some(guild_id)) => config.get_close_delay(guild_id).await?,
+        (None, None) => DEFAULT_CLOSE_DELAY_SECS,
+    };
+
+    if !config
+        .tickets
+        .set_status(&ticket, TicketStatus::PendingClose)
+        .await?
+    {
+        return Ok("This ticket is already being closed.".to_string());
+    }
+
+    let run_at = Timestamp::now().unix_timestamp() + delay;
+    let job = config
+        .tickets
+        .schedule_close(&ticket, run_at, interaction.user().id, reason)
+        .await?;
+    config
+        .tickets
+        .record_event(
+            &ticket,
+            TicketEventKind::CloseScheduled,
+            interaction.user().id,
+            None,
+            json!({ "run_at": run_at, "reason": reason }),
+        )
+        .await?;
+
+    let mut embed = CreateEmbed::new()
         .title("Closing Ticket")
-        .description("This ticket will be closed in 5 seconds. Click the button below to cancel.")
+        .description(format!(
+            "This ticket will be closed <t:{}:R>. Click the button below to cancel.",
+            run_at
+        ))
         .color(0xff0000);
+    if let Some(reason) = reason {
+        embed = embed.field("Reason", reason, false);
+    }
 
-    let button = CreateButton::new("cancel_close")
-        .label("Cancel")
-        .style(ButtonStyle::Danger);
+    let button = CreateButton::new(
+        CustomId::CancelClose {
+            job_id: Some(job.id),
+        }
+        .to_string(),
+    )
+    .label("Cancel")
+    .style(ButtonStyle::Danger);
 
     let action_row = CreateActionRow::Buttons(vec![button]);
 
-    let message = channel_id
+    channel_id
         .send_message(
             &ctx.http,
             CreateMessage::new()
@@ -167,136 +977,2078 @@ pub async fn close(
         )
         .await?;
 
-    sleep(Duration::from_secs(5)).await;
+    scheduler::spawn_job(Arc::clone(&ctx.http), Arc::clone(config), job);
+    Ok(format!("This ticket will be closed <t:{}:R>.", run_at))
+}
 
-    if let Ok(updated_message) = message.channel_id.message(&ctx.http, message.id).await {
-        if !updated_message.components.is_empty() {
-            if let Ok(Channel::Guild(guild_channel)) = channel_id.to_channel(&ctx).await {
-                log_ticket_action(ctx, "Closed", interaction.user(), &guild_channel, config)
-                    .await?;
-                channel_id.delete(&ctx.http).await?;
-                Ok("Ticket closed successfully.".to_string())
-            } else {
-                Ok("Failed to close ticket: not a guild channel.".to_string())
-            }
-        } else {
-            Ok("Ticket closure was cancelled.".to_string())
+/// Closes the ticket living in `channel_id` right away: saves and logs its
+/// transcript, lets the opener know, and then deletes or archives the channel
+/// depending on the close mode.
+/// Channels without a ticket are left alone.
+pub async fn close_channel(
+    http: &impl CacheHttp,
+    channel_id: ChannelId,
+    closed_by: &User,
+    reason: Option<&str>,
+    config: &Arc<Config>,
+) -> Result<String, TicketError> {
+    let Some(ticket) = config.tickets.find_by_channel(channel_id).await? else {
+        return Err(TicketError::NotATicket);
+    };
+    let Some(guild_channel) = ticket_channel(http, &ticket, config).await? else {
+        return Ok("The ticket channel no longer exists.".to_string());
+    };
+
+    let transcript = match transcript::build(http, &guild_channel).await {
+        Ok(transcript) => Some(transcript),
+        Err(why) => {
+            warn!(error = %why, "Failed to build transcript");
+            None
         }
-    } else {
-        Ok("Failed to check ticket status.".to_string())
+    };
+    // Once the ticket is marked closed, failing to record or log it must not
+    // keep the channel around.
+    if !config.tickets.close(&ticket, closed_by.id, reason).await? {
+        return Ok("This ticket was already closed.".to_string());
+    }
+    if let Err(why) = config
+        .tickets
+        .record_event(
+            &ticket,
+            TicketEventKind::Closed,
+            closed_by.id,
+            None,
+            json!({ "reason": reason }),
+        )
+        .await
+    {
+        warn!(error = %why, "Failed to record ticket close");
+    }
+    notify_opener(http, &ticket, &guild_channel, closed_by, reason).await;
+    if let Some(transcript) = &transcript {
+        if let Err(why) = config.tickets.save_transcript(&ticket, transcript).await {
+            warn!(error = %why, "Failed to save transcript");
+        }
+    }
+    if close_mode(guild_channel.guild_id, &ticket, config).await? == CloseMode::Archive {
+        archive_channel(http, &guild_channel, &ticket, config).await?;
+        log_close(
+            http,
+            &guild_channel,
+            closed_by,
+            reason,
+            transcript.as_ref(),
+            config,
+        )
+        .await;
+        return Ok("Ticket closed and archived.".to_string());
     }
-}
 
-pub trait InteractionContext {
-    fn channel_id(&self) -> ChannelId;
-    fn user(&self) -> &User;
+    log_close(
+        http,
+        &guild_channel,
+        closed_by,
+        reason,
+        transcript.as_ref(),
+        config,
+    )
+    .await;
+    channel_id.delete(http.http()).await?;
+    config.tickets.mark_deleted(channel_id).await?;
+    Ok("Ticket closed successfully.".to_string())
 }
 
-impl InteractionContext for CommandInteraction {
-    fn channel_id(&self) -> ChannelId {
-        self.channel_id
+/// Deletes or archives the channel of a ticket that is already closed, for a
+/// close that failed after marking it so. Channels that were archived are
+/// left alone.
+pub async fn finish_close(
+    http: &impl CacheHttp,
+    ticket: &Ticket,
+    config: &Arc<Config>,
+) -> Result<(), TicketError> {
+    let Some(channel) = ticket_channel(http, ticket, config).await? else {
+        return Ok(());
+    };
+    match close_mode(channel.guild_id, ticket, config).await? {
+        CloseMode::Archive => {
+            if config.tickets.archived_from(ticket).await?.name.is_none() {
+                archive_channel(http, &channel, ticket, config).await?;
+            }
+        }
+        CloseMode::Delete => {
+            channel.id.delete(http.http()).await?;
+            config.tickets.mark_deleted(channel.id).await?;
+        }
     }
+    Ok(())
+}
 
-    fn user(&self) -> &User {
-        &self.user
+/// The channel `ticket` lives in, or `None` after marking the ticket deleted
+/// if the channel is gone.
+async fn ticket_channel(
+    http: &impl CacheHttp,
+    ticket: &Ticket,
+    config: &Arc<Config>,
+) -> Result<Option<GuildChannel>, TicketError> {
+    match ticket.channel_id().to_channel(http).await {
+        Ok(Channel::Guild(channel)) => Ok(Some(channel)),
+        Ok(_) => Err(TicketError::NotATicket),
+        Err(SerenityError::Http(why)) if why.status_code() == Some(StatusCode::NOT_FOUND) => {
+            config.tickets.mark_deleted(ticket.channel_id()).await?;
+            Ok(None)
+        }
+        Err(why) => Err(why.into()),
     }
 }
 
-impl InteractionContext for ComponentInteraction {
-    fn channel_id(&self) -> ChannelId {
-        self.channel_id
+/// Posts the close and the transcript to the log channel. Only warns on
+/// failure, as the ticket is already closed by then.
+async fn log_close(
+    http: &impl CacheHttp,
+    channel: &GuildChannel,
+    closed_by: &User,
+    reason: Option<&str>,
+    transcript: Option<&Transcript>,
+    config: &Arc<Config>,
+) {
+    if let Err(why) = log_ticket_action(http, "Closed", closed_by, channel, reason, config).await {
+        warn!(error = %why, "Failed to log ticket close");
     }
-
-    fn user(&self) -> &User {
-        &self.user
+    if let Some(transcript) = transcript {
+        if let Err(why) = log_transcript(http, channel, transcript, config).await {
+            warn!(error = %why, "Failed to log transcript");
+        }
     }
 }
 
-pub async fn add_user(
-    ctx: &Context,
-    command: &CommandInteraction,
+/// The open ticket living in the channel `interaction` came from.
+async fn open_ticket_in(
+    interaction: &impl InteractionContext,
     config: &Arc<Config>,
-) -> Result<String, SerenityError> {
-    if let Some(_guild_id) = command.guild_id {
-        if let Some(user) = command.data.resolved.users.values().next() {
-            if let Ok(channel) = command.channel_id.to_channel(&ctx).await {
-                if let Channel::Guild(guild_channel) = channel {
-                    if let Ok(()) = guild_channel
-                        .create_permission(
-                            &ctx.http,
-                            PermissionOverwrite {
-                                allow: Permissions::VIEW_CHANNEL
-                                    | Permissions::SEND_MESSAGES
-                                    | Permissions::READ_MESSAGE_HISTORY,
-                                deny: Permissions::empty(),
-                                kind: PermissionOverwriteType::Member(user.id),
-                            },
-                        )
-                        .await
-                    {
-                        log_ticket_action(ctx, "User Added", user, &guild_channel, config).await?;
-                        Ok(format!("User {} has been added to the ticket.", user.name))
-                    } else {
-                        Err(TicketError(Cow::Borrowed("Failed to add user to the ticket.")).into())
-                    }
-                } else {
-                    Err(TicketError(Cow::Borrowed(
-                        "This command can only be used in a server channel.",
-                    ))
-                    .into())
-                }
-            } else {
-                Err(TicketError(Cow::Borrowed("Failed to fetch channel information.")).into())
-            }
-        } else {
-            Err(TicketError(Cow::Borrowed("Please mention a user to add to the ticket.")).into())
-        }
-    } else {
-        Err(TicketError(Cow::Borrowed("This command can only be used in a server.")).into())
+) -> Result<Ticket, TicketError> {
+    config
+        .tickets
+        .find_by_channel(interaction.channel_id())
+        .await?
+        .filter(|ticket| ticket.status == TicketStatus::Open)
+        .ok_or(TicketError::NotATicket)
+}
+
+/// The open ticket in the channel `interaction` came from, provided the
+/// invoking member is its opener, a participant or staff.
+async fn ticket_member_in(
+    interaction: &impl InteractionContext,
+    config: &Arc<Config>,
+) -> Result<Ticket, TicketError> {
+    let ticket = open_ticket_in(interaction, config).await?;
+    if member_level(interaction, config).await? < PermissionLevel::Participant {
+        return Err(TicketError::Permission(
+            "Only the opener, participants and staff can do that in this ticket.".into(),
+        ));
     }
+    Ok(ticket)
 }
 
-pub async fn remove_user(
+/// Assigns the ticket to the invoking staff member so nobody else picks it up,
+/// optionally leaving the other staff read-only.
+pub async fn claim(
     ctx: &Context,
-    command: &CommandInteraction,
+    interaction: &impl InteractionContext,
     config: &Arc<Config>,
-) -> Result<String, SerenityError> {
-    if let Some(_guild_id) = command.guild_id {
-        if let Some(user) = command.data.resolved.users.values().next() {
-            if let Ok(channel) = command.channel_id.to_channel(&ctx).await {
-                if let Channel::Guild(guild_channel) = channel {
-                    if let Ok(()) = guild_channel
-                        .delete_permission(&ctx.http, PermissionOverwriteType::Member(user.id))
-                        .await
-                    {
-                        log_ticket_action(ctx, "User Removed", user, &guild_channel, config)
-                            .await?;
-                        Ok(format!(
-                            "User {} has been removed from the ticket.",
-                            user.name
-                        ))
-                    } else {
-                        Err(
-                            TicketError(Cow::Borrowed("Failed to remove user from the ticket."))
-                                .into(),
-                        )
-                    }
+) -> Result<String, TicketError> {
+    let guild_id = guild_of(interaction)?;
+    let ticket = open_ticket_in(interaction, config).await?;
+    let user = interaction.user();
+
+    if let Some(claimed_by) = ticket.claimed_by() {
+        return Err(TicketError::Validation(
+            format!(
+                "This ticket is already claimed by {}.",
+                claimed_by.mention()
+            )
+            .into(),
+        ));
+    }
+    if !config.tickets.claim(&ticket, user.id).await? {
+        return Err(TicketError::Validation(
+            "Somebody else just claimed this ticket.".into(),
+        ));
+    }
+    config
+        .tickets
+        .record_event(&ticket, TicketEventKind::Claimed, user.id, None, json!({}))
+        .await?;
+
+    let Channel::Guild(channel) = interaction.channel_id().to_channel(ctx).await? else {
+        return Err(TicketError::Validation(
+            "This ticket is not a server channel.".into(),
+        ));
+    };
+    let mut edit = EditChannel::new().topic(format!("Claimed by {}", user.name));
+    if config.get_claim_read_only(guild_id).await? {
+        let mut updates = ticket_staff_overwrites(guild_id, &ticket, config)
+            .await?
+            .into_iter()
+            .map(|mut overwrite| {
+                overwrite.allow.remove(Permissions::SEND_MESSAGES);
+                overwrite.deny.insert(Permissions::SEND_MESSAGES);
+                overwrite
+            })
+            .collect::<Vec<_>>();
+        updates.push(member_overwrite(user.id));
+        edit = edit.permissions(merge_overwrites(&channel.permission_overwrites, updates));
+    }
+    let channel = channel.id.edit(&ctx.http, edit).await?;
+
+    log_ticket_action(ctx, "Claimed", user, &channel, None, config).await?;
+    Ok(format!("{} has claimed this ticket.", user.mention()))
+}
+
+/// Releases a claimed ticket and gives all staff their normal access back.
+/// Only the claimer or an admin may do this.
+pub async fn unclaim(
+    ctx: &Context,
+    interaction: &impl InteractionContext,
+    config: &Arc<Config>,
+) -> Result<String, TicketError> {
+    let guild_id = guild_of(interaction)?;
+    let ticket = open_ticket_in(interaction, config).await?;
+    let user = interaction.user();
+
+    let Some(claimed_by) = ticket.claimed_by() else {
+        return Err(TicketError::Validation(
+            "This ticket is not claimed.".into(),
+        ));
+    };
+    if claimed_by != user.id && member_level(interaction, config).await? < PermissionLevel::Admin {
+        return Err(TicketError::Permission(
+            format!(
+                "Only {} or an admin can unclaim this ticket.",
+                claimed_by.mention()
+            )
+            .into(),
+        ));
+    }
+    if !config.tickets.unclaim(&ticket).await? {
+        return Ok("This ticket is not claimed anymore.".to_string());
+    }
+    config
+        .tickets
+        .record_event(
+            &ticket,
+            TicketEventKind::Unclaimed,
+            user.id,
+            Some(claimed_by),
+            json!({}),
+        )
+        .await?;
+
+    let Channel::Guild(channel) = interaction.channel_id().to_channel(ctx).await? else {
+        return Err(TicketError::Validation(
+            "This ticket is not a server channel.".into(),
+        ));
+    };
+    let keep_claimer = claimed_by == ticket.opener_id()
+        || config.tickets.is_participant(&ticket, claimed_by).await?;
+    let existing = channel
+        .permission_overwrites
+        .iter()
+        .filter(|overwrite| {
+            keep_claimer || overwrite.kind != PermissionOverwriteType::Member(claimed_by)
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    let staff = ticket_staff_overwrites(guild_id, &ticket, config).await?;
+    let channel = channel
+        .id
+        .edit(
+            &ctx.http,
+            EditChannel::new()
+                .topic("")
+                .permissions(merge_overwrites(&existing, staff)),
+        )
+        .await?;
+
+    log_ticket_action(ctx, "Unclaimed", user, &channel, None, config).await?;
+    Ok(format!("{} has unclaimed this ticket.", user.mention()))
+}
+
+/// Whether the resolved `member` may staff `ticket`.
+async fn is_staff_member(
+    guild_id: GuildId,
+    member: &PartialMember,
+    ticket: &Ticket,
+    config: &Arc<Config>,
+) -> Result<bool, TicketError> {
+    let permissions = member.permissions.unwrap_or_else(Permissions::empty);
+    if permissions.administrator()
+        || permissions.contains(ADMIN_PERMISSIONS)
+        || permissions.manage_messages()
+    {
+        return Ok(true);
+    }
+
+    let mut roles = config.get_support_roles(guild_id).await?;
+    roles.extend(config.get_staff_roles(guild_id, ticket.type_id).await?);
+    if let Some(tier) = config
+        .get_escalation_tier(guild_id, ticket.escalation_level)
+        .await?
+    {
+        roles.push(tier.role_id());
+    }
+    Ok(member.roles.iter().any(|role| roles.contains(role)))
+}
+
+/// Makes another staff member responsible for the ticket.
+pub async fn transfer(
+    ctx: &Context,
+    command: &CommandInteraction,
+    config: &Arc<Config>,
+) -> Result<String, TicketError> {
+    let guild_id = guild_of(command)?;
+    let ticket = open_ticket_in(command, config).await?;
+    let Some((user, member)) = command
+        .data
+        .resolved
+        .users
+        .values()
+        .next()
+        .and_then(|user| Some((user, command.data.resolved.members.get(&user.id)?)))
+    else {
+        return Err(TicketError::Validation(
+            "Please mention a server member.".into(),
+        ));
+    };
+
+    if ticket.claimed_by() == Some(user.id) {
+        return Err(TicketError::Validation(
+            format!("{} is already responsible for this ticket.", user.name).into(),
+        ));
+    }
+    if user.bot || !is_staff_member(guild_id, member, &ticket, config).await? {
+        return Err(TicketError::Validation(
+            format!("{} is not a staff member.", user.name).into(),
+        ));
+    }
+
+    let Channel::Guild(channel) = command.channel_id.to_channel(ctx).await? else {
+        return Err(TicketError::Validation(
+            "This ticket is not a server channel.".into(),
+        ));
+    };
+    let previous = ticket.claimed_by();
+    let mut keep_previous = true;
+    if let Some(previous) = previous {
+        keep_previous = previous == ticket.opener_id()
+            || config.tickets.is_participant(&ticket, previous).await?;
+    }
+
+    config
+        .tickets
+        .transfer(&ticket, user.id, command.user.id)
+        .await?;
+    config
+        .tickets
+        .record_event(
+            &ticket,
+            TicketEventKind::Transferred,
+            command.user.id,
+            Some(user.id),
+            json!({ "from": previous.map(|id| id.get().to_string()) }),
+        )
+        .await?;
+
+    let existing = channel
+        .permission_overwrites
+        .iter()
+        .filter(|overwrite| {
+            keep_previous || previous.map(PermissionOverwriteType::Member) != Some(overwrite.kind)
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    let channel = channel
+        .id
+        .edit(
+            &ctx.http,
+            EditChannel::new()
+                .topic(format!("Claimed by {}", user.name))
+                .permissions(merge_overwrites(&existing, vec![member_overwrite(user.id)])),
+        )
+        .await?;
+
+    let from = previous
+        .map(|id| id.mention().to_string())
+        .unwrap_or_else(|| "nobody".to_string());
+    let to = user.mention().to_string();
+    log_ticket_details(
+        ctx,
+        "Transferred",
+        &command.user,
+        &channel,
+        &[("From", &from), ("To", &to)],
+        config,
+    )
+    .await?;
+    Ok(format!(
+        "{} is now responsible for this ticket.",
+        user.mention()
+    ))
+}
+
+/// Moves the ticket one escalation tier up: into the tier's category, staffed
+/// by the tier's role instead of the previous staff, which gets pinged.
+pub async fn escalate(
+    ctx: &Context,
+    command: &CommandInteraction,
+    reason: Option<&str>,
+    config: &Arc<Config>,
+) -> Result<String, TicketError> {
+    let guild_id = guild_of(command)?;
+    let ticket = open_ticket_in(command, config).await?;
+    let Some(tier) = config
+        .next_escalation_tier(guild_id, ticket.escalation_level)
+        .await?
+    else {
+        return Err(TicketError::Validation(
+            "This ticket is already at the highest escalation tier.".into(),
+        ));
+    };
+
+    let Channel::Guild(channel) = command.channel_id.to_channel(ctx).await? else {
+        return Err(TicketError::Validation(
+            "This ticket is not a server channel.".into(),
+        ));
+    };
+    let previous_staff = ticket_staff_overwrites(guild_id, &ticket, config).await?;
+
+    if !config
+        .tickets
+        .escalate(&ticket, tier.level, command.user.id, reason)
+        .await?
+    {
+        return Ok("This ticket was just escalated by somebody else.".to_string());
+    }
+    config
+        .tickets
+        .record_event(
+            &ticket,
+            TicketEventKind::Escalated,
+            command.user.id,
+            None,
+            json!({ "level": tier.level, "tier": tier.name, "reason": reason }),
+        )
+        .await?;
+
+    let existing = channel
+        .permission_overwrites
+        .iter()
+        .filter(|overwrite| {
+            !previous_staff
+                .iter()
+                .any(|previous| previous.kind == overwrite.kind)
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    let mut edit = EditChannel::new().permissions(merge_overwrites(
+        &existing,
+        vec![role_overwrite(tier.role_id())],
+    ));
+    if let Some(category_id) = tier.category_id() {
+        edit = edit.category(category_id);
+    }
+    let channel = channel.id.edit(&ctx.http, edit).await?;
+
+    let mut embed = CreateEmbed::new()
+        .title(format!("Escalated to {}", tier.name))
+        .description(format!("Escalated by {}.", command.user.mention()))
+        .color(0x8b0000);
+    if let Some(reason) = reason {
+        embed = embed.field("Reason", reason, false);
+    }
+    channel
+        .send_message(
+            &ctx.http,
+            CreateMessage::new()
+                .content(tier.role_id().mention().to_string())
+                .embed(embed)
+                .allowed_mentions(CreateAllowedMentions::new().roles(vec![tier.role_id()])),
+        )
+        .await?;
+
+    let level = tier.level.to_string();
+    let mut details = vec![("Tier", tier.name.as_str()), ("Level", level.as_str())];
+    if let Some(reason) = reason {
+        details.push(("Reason", reason));
+    }
+    log_ticket_details(ctx, "Escalated", &command.user, &channel, &details, config).await?;
+    Ok(format!("Ticket escalated to {}.", tier.name))
+}
+
+/// How many lines `/ticket history`, `/ticket log` and `/ticket list` show at
+/// once, keeping responses well under Discord's message length limit.
+const ENTRIES_PER_PAGE: i64 = 10;
+
+/// `/ticket`: everything done to or inside a single ticket, and looking
+/// through the guild's tickets.
+pub async fn ticket(
+    ctx: &Context,
+    command: &CommandInteraction,
+    config: &Arc<Config>,
+) -> Result<String, TicketError> {
+    let guild_id = guild_of(command)?;
+    let Some((name, options)) = subcommand(command) else {
+        return Err(TicketError::Validation(
+            "Please choose a subcommand.".into(),
+        ));
+    };
+    let page = option_int(&options, "page").unwrap_or(1).max(1);
+
+    match name {
+        "open" => open_ticket_command(ctx, command, option_str(&options, "type"), config).await,
+        "close" => {
+            let delay = option_str(&options, "delay")
+                .map(parse_duration)
+                .transpose()?;
+            close(ctx, command, option_str(&options, "reason"), delay, config).await
+        }
+        "add" => add_user(ctx, command, config).await,
+        "remove" => remove_user(ctx, command, config).await,
+        "rename" => rename_ticket(ctx, command, option_str(&options, "name"), config).await,
+        "claim" => claim(ctx, command, config).await,
+        "unclaim" => unclaim(ctx, command, config).await,
+        "transfer" => transfer(ctx, command, config).await,
+        "escalate" => escalate(ctx, command, option_str(&options, "reason"), config).await,
+        "reopen" => reopen(ctx, command, config).await,
+        "list" => list_tickets(command, config).await,
+        "history" => ticket_history(command, page, config).await,
+        "log" => {
+            let Some(user) = option_user(&options, "user") else {
+                return Err(TicketError::Validation("Please mention a user.".into()));
+            };
+            ticket_log(guild_id, user, page, config).await
+        }
+        _ => Err(TicketError::Validation(
+            "Please choose a subcommand.".into(),
+        )),
+    }
+}
+
+async fn ticket_history(
+    command: &CommandInteraction,
+    page: i64,
+    config: &Arc<Config>,
+) -> Result<String, TicketError> {
+    let Some(ticket) = config.tickets.find_by_channel(command.channel_id).await? else {
+        return Err(TicketError::NotATicket);
+    };
+    let total = config.tickets.count_events(&ticket).await?;
+    let pages = page_count(total);
+    if page > pages {
+        return Err(TicketError::Validation(
+            format!("This ticket's history only has {} page(s).", pages).into(),
+        ));
+    }
+
+    let events = config
+        .tickets
+        .list_events(&ticket, ENTRIES_PER_PAGE, (page - 1) * ENTRIES_PER_PAGE)
+        .await?;
+    if events.is_empty() {
+        return Ok("Nothing has been recorded for this ticket yet.".to_string());
+    }
+    let lines = events
+        .iter()
+        .map(|event| format_event(event, false))
+        .collect::<Vec<_>>();
+    Ok(format!(
+        "History of this ticket (page {} of {}):\n{}",
+        page,
+        pages,
+        lines.join("\n")
+    ))
+}
+
+async fn ticket_log(
+    guild_id: GuildId,
+    user: &User,
+    page: i64,
+    config: &Arc<Config>,
+) -> Result<String, TicketError> {
+    let total = config.tickets.count_user_events(guild_id, user.id).await?;
+    let pages = page_count(total);
+    if page > pages {
+        return Err(TicketError::Validation(
+            format!("{}'s log only has {} page(s).", user.name, pages).into(),
+        ));
+    }
+
+    let events = config
+        .tickets
+        .list_user_events(
+            guild_id,
+            user.id,
+            ENTRIES_PER_PAGE,
+            (page - 1) * ENTRIES_PER_PAGE,
+        )
+        .await?;
+    if events.is_empty() {
+        return Ok(format!("No ticket events involve {}.", user.mention()));
+    }
+    let lines = events
+        .iter()
+        .map(|event| format_event(event, true))
+        .collect::<Vec<_>>();
+    Ok(format!(
+        "Ticket events involving {} (page {} of {}):\n{}",
+        user.mention(),
+        page,
+        pages,
+        lines.join("\n")
+    ))
+}
+
+/// `/ticket open`: opens a ticket for the invoking member without going
+/// through the panel. Types with an intake form still need the panel, since a
+/// deferred command cannot show the form.
+async fn open_ticket_command(
+    ctx: &Context,
+    command: &CommandInteraction,
+    type_name: Option<&str>,
+    config: &Arc<Config>,
+) -> Result<String, TicketError> {
+    let guild_id = guild_of(command)?;
+    let ticket_type = match type_name {
+        Some(name) => Some(config.find_ticket_type(guild_id, name).await?.ok_or(
+            TicketError::Validation(format!("There is no ticket type named {}.", name).into()),
+        )?),
+        None => None,
+    };
+    if let Some(ticket_type) = &ticket_type {
+        if !config
+            .get_ticket_questions(ticket_type.id)
+            .await?
+            .is_empty()
+        {
+            return Err(TicketError::Validation(
+                format!(
+                    "{} tickets ask a few questions first, please open one from the ticket panel.",
+                    ticket_type.name
+                )
+                .into(),
+            ));
+        }
+    }
+    open_within_limits(
+        ctx,
+        &command.user,
+        guild_id,
+        ticket_type.as_ref(),
+        &[],
+        config,
+    )
+    .await
+}
+
+/// `/ticket rename`: renames the ticket channel.
+async fn rename_ticket(
+    ctx: &Context,
+    command: &CommandInteraction,
+    name: Option<&str>,
+    config: &Arc<Config>,
+) -> Result<String, TicketError> {
+    let ticket = open_ticket_in(command, config).await?;
+    let name = sanitize_channel_name(name.unwrap_or_default());
+    if name.is_empty() {
+        return Err(TicketError::Validation(
+            "Please provide a valid channel name.".into(),
+        ));
+    }
+    let Channel::Guild(channel) = command.channel_id.to_channel(ctx).await? else {
+        return Err(TicketError::Validation(
+            "This ticket is not a server channel.".into(),
+        ));
+    };
+    if channel.name == name {
+        return Ok(format!("This ticket is already named {}.", name));
+    }
+
+    let old_name = channel.name.clone();
+    let channel = channel
+        .id
+        .edit(&ctx.http, EditChannel::new().name(&name))
+        .await?;
+    config
+        .tickets
+        .record_event(
+            &ticket,
+            TicketEventKind::Renamed,
+            command.user.id,
+            None,
+            json!({ "old_name": old_name, "new_name": name }),
+        )
+        .await?;
+
+    log_ticket_details(
+        ctx,
+        "Renamed",
+        &command.user,
+        &channel,
+        &[("Old Name", &old_name), ("New Name", &name)],
+        config,
+    )
+    .await?;
+    Ok(format!("Ticket renamed to {}.", channel.mention()))
+}
+
+fn page_count(total: i64) -> i64 {
+    ((total + ENTRIES_PER_PAGE - 1) / ENTRIES_PER_PAGE).max(1)
+}
+
+/// One audit log entry as a line of text, prefixed with its ticket channel
+/// when `with_ticket` is set.
+fn format_event(event: &TicketEvent, with_ticket: bool) -> String {
+    let mut line = format!("<t:{}:f> ", event.created_at);
+    if with_ticket {
+        line.push_str(&format!("{} ", event.channel_id().mention()));
+    }
+    line.push_str(&format!(
+        "**{}** by {}",
+        event.kind.label(),
+        event.actor_id().mention()
+    ));
+    if let Some(target) = event.target_id() {
+        line.push_str(&format!(" → {}", target.mention()));
+    }
+
+    let details = event
+        .payload()
+        .into_iter()
+        .filter_map(|(key, value)| {
+            let value = match (key.as_str(), value) {
+                (_, serde_json::Value::Null) => return None,
+                ("from", serde_json::Value::String(id)) => format!("<@{}>", id),
+                ("run_at", serde_json::Value::Number(at)) => format!("<t:{}:R>", at),
+                (_, serde_json::Value::String(value)) => value.chars().take(100).collect(),
+                (_, value) => value.to_string(),
+            };
+            Some(format!("{}: {}", key.replace('_', " "), value))
+        })
+        .collect::<Vec<_>>();
+    if !details.is_empty() {
+        line.push_str(&format!(" ({})", details.join("; ")));
+    }
+    line
+}
+
+/// The guild `interaction` happened in. Commands are registered without DM
+/// support, so this only fails for interactions that arrive from elsewhere.
+pub fn guild_of(interaction: &impl InteractionContext) -> Result<GuildId, TicketError> {
+    interaction.guild_id().ok_or(TicketError::Validation(
+        "This can only be used in a server.".into(),
+    ))
+}
+
+pub trait InteractionContext {
+    fn channel_id(&self) -> ChannelId;
+    fn guild_id(&self) -> Option<GuildId>;
+    fn user(&self) -> &User;
+    fn member(&self) -> Option<&Member>;
+}
+
+impl InteractionContext for CommandInteraction {
+    fn channel_id(&self) -> ChannelId {
+        self.channel_id
+    }
+
+    fn guild_id(&self) -> Option<GuildId> {
+        self.guild_id
+    }
+
+    fn user(&self) -> &User {
+        &self.user
+    }
+
+    fn member(&self) -> Option<&Member> {
+        self.member.as_deref()
+    }
+}
+
+impl InteractionContext for ModalInteraction {
+    fn channel_id(&self) -> ChannelId {
+        self.channel_id
+    }
+
+    fn guild_id(&self) -> Option<GuildId> {
+        self.guild_id
+    }
+
+    fn user(&self) -> &User {
+        &self.user
+    }
+
+    fn member(&self) -> Option<&Member> {
+        self.member.as_ref()
+    }
+}
+
+impl InteractionContext for ComponentInteraction {
+    fn channel_id(&self) -> ChannelId {
+        self.channel_id
+    }
+
+    fn guild_id(&self) -> Option<GuildId> {
+        self.guild_id
+    }
+
+    fn user(&self) -> &User {
+        &self.user
+    }
+
+    fn member(&self) -> Option<&Member> {
+        self.member.as_ref()
+    }
+}
+
+pub async fn list_tickets(
+    command: &CommandInteraction,
+    config: &Arc<Config>,
+) -> Result<String, TicketError> {
+    let guild_id = guild_of(command)?;
+
+    let options = leaf_options(command);
+    let search = option_str(&options, "search");
+    let page = option_int(&options, "page").unwrap_or(1).max(1);
+    let tickets = match search {
+        Some(query) => config.tickets.search_answers(guild_id, query).await,
+        None => config.tickets.list_open(guild_id).await,
+    }?;
+
+    if tickets.is_empty() {
+        return Ok(match search {
+            Some(_) => "No tickets match that search.".to_string(),
+            None => "There are no open tickets.".to_string(),
+        });
+    }
+
+    let pages = page_count(tickets.len() as i64);
+    if page > pages {
+        return Err(TicketError::Validation(
+            format!("There are only {} page(s) of tickets.", pages).into(),
+        ));
+    }
+
+    let lines = tickets
+        .iter()
+        .skip(((page - 1) * ENTRIES_PER_PAGE) as usize)
+        .take(ENTRIES_PER_PAGE as usize)
+        .map(|ticket| {
+            format!(
+                "{}{} opened by {} <t:{}:R>{}{}",
+                ticket
+                    .number
+                    .map(|number| format!("#{} ", number))
+                    .unwrap_or_default(),
+                ticket.channel_id().mention(),
+                ticket.opener_id().mention(),
+                ticket.created_at,
+                ticket
+                    .claimed_by()
+                    .map(|id| format!(", claimed by {}", id.mention()))
+                    .unwrap_or_default(),
+                match ticket.status {
+                    TicketStatus::Open => "",
+                    TicketStatus::PendingClose => " (closing)",
+                    TicketStatus::Closed => " (closed)",
+                    TicketStatus::Deleted => " (deleted)",
+                }
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let heading = match search {
+        Some(query) => format!(
+            "Tickets with answers matching \"{}\" (page {} of {}):",
+            query, page, pages
+        ),
+        None => format!("Open tickets (page {} of {}):", page, pages),
+    };
+    Ok(format!("{}\n{}", heading, lines.join("\n")))
+}
+
+/// Gives `user` access to the ticket the command was run in.
+pub async fn add_user(
+    ctx: &Context,
+    command: &CommandInteraction,
+    config: &Arc<Config>,
+) -> Result<String, TicketError> {
+    let ticket = ticket_member_in(command, config).await?;
+    let Some(user) = command.data.resolved.users.values().next() else {
+        return Err(TicketError::Validation(
+            "Please mention a user to add to the ticket.".into(),
+        ));
+    };
+    if user.id == ticket.opener_id() || config.tickets.is_participant(&ticket, user.id).await? {
+        return Err(TicketError::Validation(
+            format!("{} is already in this ticket.", user.name).into(),
+        ));
+    }
+    let Channel::Guild(channel) = command.channel_id.to_channel(ctx).await? else {
+        return Err(TicketError::Validation(
+            "This ticket is not a server channel.".into(),
+        ));
+    };
+
+    channel
+        .create_permission(&ctx.http, member_overwrite(user.id))
+        .await?;
+    config
+        .tickets
+        .add_participant(&ticket, user.id, command.user.id)
+        .await?;
+    config
+        .tickets
+        .record_event(
+            &ticket,
+            TicketEventKind::UserAdded,
+            command.user.id,
+            Some(user.id),
+            json!({}),
+        )
+        .await?;
+
+    log_ticket_action(ctx, "User Added", user, &channel, None, config).await?;
+    Ok(format!("User {} has been added to the ticket.", user.name))
+}
+
+/// Takes away the access `user` was given with `/ticket add`. The opener cannot
+/// be removed from their own ticket.
+pub async fn remove_user(
+    ctx: &Context,
+    command: &CommandInteraction,
+    config: &Arc<Config>,
+) -> Result<String, TicketError> {
+    let ticket = ticket_member_in(command, config).await?;
+    let Some(user) = command.data.resolved.users.values().next() else {
+        return Err(TicketError::Validation(
+            "Please mention a user to remove from the ticket.".into(),
+        ));
+    };
+    if user.id == ticket.opener_id() {
+        return Err(TicketError::Validation(
+            "The opener cannot be removed from their own ticket.".into(),
+        ));
+    }
+    let Channel::Guild(channel) = command.channel_id.to_channel(ctx).await? else {
+        return Err(TicketError::Validation(
+            "This ticket is not a server channel.".into(),
+        ));
+    };
+
+    channel
+        .delete_permission(&ctx.http, PermissionOverwriteType::Member(user.id))
+        .await?;
+    config.tickets.remove_participant(&ticket, user.id).await?;
+    config
+        .tickets
+        .record_event(
+            &ticket,
+            TicketEventKind::UserRemoved,
+            command.user.id,
+            Some(user.id),
+            json!({}),
+        )
+        .await?;
+
+    log_ticket_action(ctx, "User Removed", user, &channel, None, config).await?;
+    Ok(format!(
+        "User {} has been removed from the ticket.",
+        user.name
+    ))
+}
+
+/// Splits a command that uses subcommands into the subcommand name and its
+/// options, looking inside the subcommand group if there is one.
+fn subcommand<'a>(command: &'a CommandInteraction) -> Option<(&'a str, Vec<ResolvedOption<'a>>)> {
+    let mut options = command.data.options();
+    loop {
+        let option = options.into_iter().next()?;
+        match option.value {
+            ResolvedValue::SubCommand(inner) => return Some((option.name, inner)),
+            ResolvedValue::SubCommandGroup(inner) => options = inner,
+            _ => return None,
+        }
+    }
+}
+
+/// The options of the invoked command, or of its subcommand if it has one, so
+/// handlers shared by a flat command and a subcommand can read them the same.
+fn leaf_options<'a>(command: &'a CommandInteraction) -> Vec<ResolvedOption<'a>> {
+    match subcommand(command) {
+        Some((_, options)) => options,
+        None => command.data.options(),
+    }
+}
+
+/// A string option of the invoked command or subcommand.
+pub fn command_option_str<'a>(command: &'a CommandInteraction, name: &str) -> Option<&'a str> {
+    option_str(&leaf_options(command), name)
+}
+
+fn option_str<'a>(options: &[ResolvedOption<'a>], name: &str) -> Option<&'a str> {
+    options
+        .iter()
+        .find(|option| option.name == name)
+        .and_then(|option| match option.value {
+            ResolvedValue::String(value) => Some(value),
+            _ => None,
+        })
+}
+
+fn option_int(options: &[ResolvedOption<'_>], name: &str) -> Option<i64> {
+    options
+        .iter()
+        .find(|option| option.name == name)
+        .and_then(|option| match option.value {
+            ResolvedValue::Integer(value) => Some(value),
+            _ => None,
+        })
+}
+
+fn option_bool(options: &[ResolvedOption<'_>], name: &str) -> Option<bool> {
+    options
+        .iter()
+        .find(|option| option.name == name)
+        .and_then(|option| match option.value {
+            ResolvedValue::Boolean(value) => Some(value),
+            _ => None,
+        })
+}
+
+fn option_user<'a>(options: &[ResolvedOption<'a>], name: &str) -> Option<&'a User> {
+    options
+        .iter()
+        .find(|option| option.name == name)
+        .and_then(|option| match option.value {
+            ResolvedValue::User(user, _) => Some(user),
+            _ => None,
+        })
+}
+
+fn option_role<'a>(options: &[ResolvedOption<'a>], name: &str) -> Option<&'a Role> {
+    options
+        .iter()
+        .find(|option| option.name == name)
+        .and_then(|option| match option.value {
+            ResolvedValue::Role(role) => Some(role),
+            _ => None,
+        })
+}
+
+fn option_channel<'a>(options: &[ResolvedOption<'a>], name: &str) -> Option<&'a PartialChannel> {
+    options
+        .iter()
+        .find(|option| option.name == name)
+        .and_then(|option| match option.value {
+            ResolvedValue::Channel(channel) => Some(channel),
+            _ => None,
+        })
+}
+
+/// The id of the category picked in the option `name`, checked the same way
+/// as `/ticketconfig category`.
+async fn option_category(
+    ctx: &Context,
+    guild_id: GuildId,
+    options: &[ResolvedOption<'_>],
+    name: &str,
+) -> Result<Option<i64>, TicketError> {
+    let Some(channel) = option_channel(options, name) else {
+        return Ok(None);
+    };
+    let category = ticket_category(ctx, guild_id, channel.id).await?;
+    Ok(Some(category.id.get() as i64))
+}
+
+fn parse_close_mode(mode: &str) -> CloseMode {
+    match mode {
+        "archive" => CloseMode::Archive,
+        _ => CloseMode::Delete,
+    }
+}
+
+pub fn format_roles(roles: &[RoleId]) -> String {
+    roles
+        .iter()
+        .map(|role| role.mention().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// `/ticketconfig`: the guild-wide ticket setup.
+pub async fn ticket_config(
+    ctx: &Context,
+    command: &CommandInteraction,
+    config: &Arc<Config>,
+) -> Result<String, TicketError> {
+    match command
+        .data
+        .options
+        .first()
+        .map(|option| option.name.as_str())
+    {
+        Some("category") => set_category(ctx, command, config).await,
+        Some("logchannel") => set_log_channel(ctx, command, config).await,
+        Some("panel") => init(ctx, command, config).await,
+        Some("roles") => support_role(command, config).await,
+        Some("types") => ticket_type(ctx, command, config).await,
+        Some("limits") => ticket_limits(command, config).await,
+        Some("template") => ticket_template(command, config).await,
+        Some("archive") => ticket_archive(ctx, command, config).await,
+        Some("claims") => ticket_claims(command, config).await,
+        Some("escalation") => escalation_tier(ctx, command, config).await,
+        Some("idle") => ticket_idle(command, config).await,
+        Some("closedelay") => ticket_close_delay(command, config).await,
+        Some("questions") => ticket_question(command, config).await,
+        Some("show") => show_config(ctx, command, config).await,
+        _ => Err(TicketError::Validation(
+            "Please choose a subcommand.".into(),
+        )),
+    }
+}
+
+/// `/ticketconfig show`: the settings that are in effect for the guild,
+/// defaults included, and whether the bot can actually use the configured
+/// channels.
+async fn show_config(
+    ctx: &Context,
+    command: &CommandInteraction,
+    config: &Arc<Config>,
+) -> Result<String, TicketError> {
+    let guild_id = guild_of(command)?;
+
+    let category = match config.get_category_id(guild_id).await? {
+        Some(id) => configured_channel(ctx, id, CATEGORY_PERMISSIONS).await?,
+        None => "not set, tickets are created outside any category".to_string(),
+    };
+    let log_channel = match config.get_log_channel_id(guild_id).await? {
+        Some(id) => configured_channel(ctx, id, LOG_CHANNEL_PERMISSIONS).await?,
+        None => "not set, nothing is logged".to_string(),
+    };
+    let roles = config.get_support_roles(guild_id).await?;
+    let ticket_types = config
+        .get_ticket_types(guild_id)
+        .await?
+        .into_iter()
+        .map(|ticket_type| ticket_type.name)
+        .collect::<Vec<_>>();
+    let template = config
+        .get_channel_name_template(guild_id)
+        .await?
+        .unwrap_or_else(|| DEFAULT_CHANNEL_NAME_TEMPLATE.to_string());
+
+    let on_close = match config.get_close_mode(guild_id).await? {
+        CloseMode::Delete => "deleted".to_string(),
+        CloseMode::Archive => {
+            let retention = config.get_archive_retention_hours(guild_id).await?;
+            format!(
+                "archived in {} and {}",
+                config
+                    .get_archive_category_id(guild_id)
+                    .await?
+                    .map(|id| ChannelId::new(id as u64).mention().to_string())
+                    .unwrap_or_else(|| "their current category".to_string()),
+                if retention == 0 {
+                    "kept forever".to_string()
                 } else {
-                    Err(TicketError(Cow::Borrowed(
-                        "This command can only be used in a server channel.",
-                    ))
-                    .into())
+                    format!("deleted after {} hour(s)", retention)
                 }
+            )
+        }
+    };
+    let idle = match config.get_idle_close_hours(guild_id).await? {
+        Some(hours) => format!(
+            "warned after {} hour(s) without messages, closed {} hour(s) later",
+            hours,
+            config.get_idle_grace_hours(guild_id).await?
+        ),
+        None => "never closed".to_string(),
+    };
+    let tiers = config
+        .get_escalation_tiers(guild_id)
+        .await?
+        .into_iter()
+        .map(|tier| format!("{}. {}", tier.level, tier.name))
+        .collect::<Vec<_>>();
+
+    let lines = [
+        format!("**Category:** {}", category),
+        format!("**Log channel:** {}", log_channel),
+        format!(
+            "**Support roles:** {}",
+            if roles.is_empty() {
+                "none, only admins see every ticket".to_string()
             } else {
-                Err(TicketError(Cow::Borrowed("Failed to fetch channel information.")).into())
+                format_roles(&roles)
             }
-        } else {
-            Err(TicketError(Cow::Borrowed(
-                "Please mention a user to remove from the ticket.",
+        ),
+        format!(
+            "**Ticket types:** {}",
+            if ticket_types.is_empty() {
+                "none".to_string()
+            } else {
+                ticket_types.join(", ")
+            }
+        ),
+        format!("**Channel names:** `{}`", template),
+        format!(
+            "**Limits:** {} open ticket(s) per member, {} second(s) between tickets",
+            config.get_max_open_tickets(guild_id).await?,
+            config.get_ticket_cooldown(guild_id).await?
+        ),
+        format!(
+            "**Closing:** after {} second(s), then {}",
+            config.get_close_delay(guild_id).await?,
+            on_close
+        ),
+        format!("**Inactive tickets:** {}", idle),
+        format!(
+            "**Claims:** other staff {}",
+            if config.get_claim_read_only(guild_id).await? {
+                "become read-only"
+            } else {
+                "keep full access"
+            }
+        ),
+        format!(
+            "**Escalation tiers:** {}",
+            if tiers.is_empty() {
+                "none".to_string()
+            } else {
+                tiers.join(", ")
+            }
+        ),
+    ];
+    Ok(lines.join("\n"))
+}
+
+/// A configured channel as shown by `/ticketconfig show`, flagging it when it
+/// is gone or the bot lacks some of `required` in it.
+pub async fn configured_channel(
+    ctx: &Context,
+    id: i64,
+    required: Permissions,
+) -> Result<String, TicketError> {
+    let channel_id = ChannelId::new(id as u64);
+    let Ok(Channel::Guild(channel)) = channel_id.to_channel(ctx).await else {
+        return Ok(format!("{} (cannot be found)", channel_id.mention()));
+    };
+
+    let missing = missing_bot_permissions(ctx, &channel, required).await?;
+    if missing.is_empty() {
+        Ok(channel.mention().to_string())
+    } else {
+        Ok(format!(
+            "{} (missing {})",
+            channel.mention(),
+            missing.get_permission_names().join(", ")
+        ))
+    }
+}
+
+pub async fn support_role(
+    command: &CommandInteraction,
+    config: &Arc<Config>,
+) -> Result<String, TicketError> {
+    let guild_id = guild_of(command)?;
+    let Some((name, options)) = subcommand(command) else {
+        return Err(TicketError::Validation(
+            "Please choose a subcommand.".into(),
+        ));
+    };
+
+    match (name, option_role(&options, "role")) {
+        ("add", Some(role)) => {
+            if config.add_support_role(guild_id, role.id).await? {
+                Ok(format!("{} is now a support role.", role.name))
+            } else {
+                Ok(format!("{} is already a support role.", role.name))
+            }
+        }
+        ("remove", Some(role)) => {
+            if config.remove_support_role(guild_id, role.id).await? {
+                Ok(format!("{} is no longer a support role.", role.name))
+            } else {
+                Ok(format!("{} is not a support role.", role.name))
+            }
+        }
+        ("list", _) => {
+            let roles = config.get_support_roles(guild_id).await?;
+            if roles.is_empty() {
+                Ok("No support roles are configured.".to_string())
+            } else {
+                Ok(format!("Support roles: {}", format_roles(&roles)))
+            }
+        }
+        _ => Err(TicketError::Validation("Please mention a role.".into())),
+    }
+}
+
+pub async fn ticket_type(
+    ctx: &Context,
+    command: &CommandInteraction,
+    config: &Arc<Config>,
+) -> Result<String, TicketError> {
+    let guild_id = guild_of(command)?;
+    let Some((subcommand, options)) = subcommand(command) else {
+        return Err(TicketError::Validation(
+            "Please choose a subcommand.".into(),
+        ));
+    };
+
+    if subcommand == "list" {
+        let ticket_types = config.get_ticket_types(guild_id).await?;
+        if ticket_types.is_empty() {
+            return Ok("No ticket types are configured.".to_string());
+        }
+
+        let mut lines = Vec::new();
+        for ticket_type in &ticket_types {
+            let roles = config.get_ticket_type_roles(ticket_type.id).await?;
+            lines.push(format!(
+                "**{}**: category {}, staff {}, channel name `{}`, limit {}, on close {}{}",
+                ticket_type.name,
+                ticket_type
+                    .category_id()
+                    .map(|id| id.mention().to_string())
+                    .unwrap_or_else(|| "default".to_string()),
+                if roles.is_empty() {
+                    "default".to_string()
+                } else {
+                    format_roles(&roles)
+                },
+                ticket_type
+                    .channel_name_template
+                    .as_deref()
+                    .unwrap_or("server default"),
+                ticket_type
+                    .max_open_tickets
+                    .map(|max| max.to_string())
+                    .unwrap_or_else(|| "default".to_string()),
+                match ticket_type.close_mode {
+                    Some(CloseMode::Delete) => "delete",
+                    Some(CloseMode::Archive) => "archive",
+                    None => "default",
+                },
+                if ticket_type.require_close_reason {
+                    ", close reason required"
+                } else {
+                    ""
+                }
+            ));
+        }
+        return Ok(lines.join("\n"));
+    }
+
+    let Some(name) = option_str(&options, "name") else {
+        return Err(TicketError::Validation(
+            "Please provide a ticket type name.".into(),
+        ));
+    };
+    let fields = TicketTypeFields {
+        emoji: option_str(&options, "emoji"),
+        category_id: option_category(ctx, guild_id, &options, "category").await?,
+        welcome_message: option_str(&options, "welcome"),
+        channel_name_template: option_str(&options, "template"),
+        max_open_tickets: option_int(&options, "limit"),
+        require_close_reason: option_bool(&options, "require_reason"),
+        close_mode: option_str(&options, "close_mode").map(parse_close_mode),
+    };
+
+    if subcommand == "create" {
+        if config.find_ticket_type(guild_id, name).await?.is_some() {
+            return Err(TicketError::Validation(
+                format!("A ticket type named {} already exists.", name).into(),
+            ));
+        }
+
+        let ticket_type = config.create_ticket_type(guild_id, name, &fields).await?;
+        return Ok(format!(
+            "Ticket type {} created. Run `/ticketconfig panel` to update the ticket panel.",
+            ticket_type.name
+        ));
+    }
+
+    let Some(ticket_type) = config.find_ticket_type(guild_id, name).await? else {
+        return Err(TicketError::Validation(
+            format!("There is no ticket type named {}.", name).into(),
+        ));
+    };
+
+    match (subcommand, option_role(&options, "role")) {
+        ("edit", _) => {
+            config.update_ticket_type(ticket_type.id, &fields).await?;
+            Ok(format!("Ticket type {} updated.", ticket_type.name))
+        }
+        ("delete", _) => {
+            config.delete_ticket_type(ticket_type.id).await?;
+            Ok(format!(
+                "Ticket type {} deleted. Run `/ticketconfig panel` to update the ticket panel.",
+                ticket_type.name
+            ))
+        }
+        ("addrole", Some(role)) => {
+            config.add_ticket_type_role(ticket_type.id, role.id).await?;
+            Ok(format!(
+                "{} now staffs {} tickets.",
+                role.name, ticket_type.name
+            ))
+        }
+        ("removerole", Some(role)) => {
+            config
+                .remove_ticket_type_role(ticket_type.id, role.id)
+                .await?;
+            Ok(format!(
+                "{} no longer staffs {} tickets.",
+                role.name, ticket_type.name
+            ))
+        }
+        _ => Err(TicketError::Validation("Please mention a role.".into())),
+    }
+}
+
+pub async fn ticket_question(
+    command: &CommandInteraction,
+    config: &Arc<Config>,
+) -> Result<String, TicketError> {
+    let guild_id = guild_of(command)?;
+    let Some((subcommand, options)) = subcommand(command) else {
+        return Err(TicketError::Validation(
+            "Please choose a subcommand.".into(),
+        ));
+    };
+    let Some(name) = option_str(&options, "type") else {
+        return Err(TicketError::Validation(
+            "Please provide a ticket type name.".into(),
+        ));
+    };
+    let Some(ticket_type) = config.find_ticket_type(guild_id, name).await? else {
+        return Err(TicketError::Validation(
+            format!("There is no ticket type named {}.", name).into(),
+        ));
+    };
+
+    let questions = config.get_ticket_questions(ticket_type.id).await?;
+
+    match subcommand {
+        "add" => {
+            if questions.len() >= 5 {
+                return Err(TicketError::Validation(
+                    "A ticket type can have at most 5 questions.".into(),
+                ));
+            }
+
+            let Some(label) = option_str(&options, "question") else {
+                return Err(TicketError::Validation("Please provide a question.".into()));
+            };
+            if label.chars().count() > 45 {
+                return Err(TicketError::Validation(
+                    "Questions can be at most 45 characters long.".into(),
+                ));
+            }
+
+            let style = match option_str(&options, "style") {
+                Some("paragraph") => QuestionStyle::Paragraph,
+                _ => QuestionStyle::Short,
+            };
+            let required = option_bool(&options, "required").unwrap_or(true);
+            let placeholder = option_str(&options, "placeholder");
+
+            let position = config
+                .add_ticket_question(ticket_type.id, label, style, required, placeholder)
+                .await?;
+            Ok(format!(
+                "Added question {} to {} tickets.",
+                position, ticket_type.name
             ))
-            .into())
         }
+        "remove" => {
+            let Some(position) = option_int(&options, "position") else {
+                return Err(TicketError::Validation(
+                    "Please provide a question number.".into(),
+                ));
+            };
+
+            if config
+                .remove_ticket_question(ticket_type.id, position)
+                .await?
+            {
+                Ok(format!(
+                    "Removed question {} from {} tickets.",
+                    position, ticket_type.name
+                ))
+            } else {
+                Ok(format!(
+                    "{} tickets have no question {}.",
+                    ticket_type.name, position
+                ))
+            }
+        }
+        "list" => {
+            if questions.is_empty() {
+                return Ok(format!("{} tickets have no questions.", ticket_type.name));
+            }
+
+            let lines = questions
+                .iter()
+                .map(|question| {
+                    format!(
+                        "{}. {} ({}{})",
+                        question.position,
+                        question.label,
+                        match question.style {
+                            QuestionStyle::Short => "short",
+                            QuestionStyle::Paragraph => "paragraph",
+                        },
+                        if question.required { ", required" } else { "" }
+                    )
+                })
+                .collect::<Vec<_>>();
+            Ok(lines.join("\n"))
+        }
+        _ => Err(TicketError::Validation("Unknown subcommand.".into())),
+    }
+}
+
+pub async fn ticket_limits(
+    command: &CommandInteraction,
+    config: &Arc<Config>,
+) -> Result<String, TicketError> {
+    let guild_id = guild_of(command)?;
+    let options = leaf_options(command);
+
+    if let Some(max_open) = option_int(&options, "max_open") {
+        config.set_max_open_tickets(guild_id, max_open).await?;
+    }
+    if let Some(cooldown) = option_int(&options, "cooldown") {
+        config.set_ticket_cooldown(guild_id, cooldown).await?;
+    }
+
+    let max_open = config.get_max_open_tickets(guild_id).await?;
+    let cooldown = config.get_ticket_cooldown(guild_id).await?;
+    Ok(format!(
+        "Members can have {} open ticket(s) at once and must wait {} second(s) between tickets.",
+        max_open, cooldown
+    ))
+}
+
+pub async fn ticket_template(
+    command: &CommandInteraction,
+    config: &Arc<Config>,
+) -> Result<String, TicketError> {
+    let guild_id = guild_of(command)?;
+    let options = leaf_options(command);
+
+    if let Some(template) = option_str(&options, "template") {
+        if sanitize_channel_name(template).is_empty() {
+            return Err(TicketError::Validation(
+                "That template does not produce a valid channel name.".into(),
+            ));
+        }
+        config.set_channel_name_template(guild_id, template).await?;
+    }
+
+    let template = config
+        .get_channel_name_template(guild_id)
+        .await?
+        .unwrap_or_else(|| DEFAULT_CHANNEL_NAME_TEMPLATE.to_string());
+    Ok(format!(
+        "New ticket channels are named `{}`. Available placeholders: `{{number}}`, `{{user}}`, \
+        `{{userid}}` and `{{type}}`.",
+        template
+    ))
+}
+
+pub async fn ticket_archive(
+    ctx: &Context,
+    command: &CommandInteraction,
+    config: &Arc<Config>,
+) -> Result<String, TicketError> {
+    let guild_id = guild_of(command)?;
+    let options = leaf_options(command);
+    let category_id = option_category(ctx, guild_id, &options, "category").await?;
+
+    if let Some(mode) = option_str(&options, "mode") {
+        config
+            .set_close_mode(guild_id, parse_close_mode(mode))
+            .await?;
+    }
+    if let Some(category_id) = category_id {
+        config
+            .set_archive_category_id(guild_id, category_id)
+            .await?;
+    }
+    if let Some(retention) = option_int(&options, "retention") {
+        config
+            .set_archive_retention_hours(guild_id, retention)
+            .await?;
+    }
+
+    let mode = config.get_close_mode(guild_id).await?;
+    if mode == CloseMode::Delete {
+        return Ok("Closed tickets are deleted right away.".to_string());
+    }
+
+    let category = config
+        .get_archive_category_id(guild_id)
+        .await?
+        .map(|id| ChannelId::new(id as u64).mention().to_string())
+        .unwrap_or_else(|| "their current category".to_string());
+    let retention = config.get_archive_retention_hours(guild_id).await?;
+    Ok(format!(
+        "Closed tickets are archived in {} and {}.",
+        category,
+        if retention == 0 {
+            "kept forever".to_string()
+        } else {
+            format!("deleted after {} hour(s)", retention)
+        }
+    ))
+}
+
+pub async fn ticket_claims(
+    command: &CommandInteraction,
+    config: &Arc<Config>,
+) -> Result<String, TicketError> {
+    let guild_id = guild_of(command)?;
+    let options = leaf_options(command);
+
+    if let Some(read_only) = option_bool(&options, "read_only") {
+        config.set_claim_read_only(guild_id, read_only).await?;
+    }
+
+    let read_only = config.get_claim_read_only(guild_id).await?;
+    Ok(if read_only {
+        "Other staff become read-only when a ticket is claimed.".to_string()
+    } else {
+        "Other staff can keep writing in claimed tickets.".to_string()
+    })
+}
+
+pub async fn escalation_tier(
+    ctx: &Context,
+    command: &CommandInteraction,
+    config: &Arc<Config>,
+) -> Result<String, TicketError> {
+    let guild_id = guild_of(command)?;
+    let Some((subcommand, options)) = subcommand(command) else {
+        return Err(TicketError::Validation(
+            "Please choose a subcommand.".into(),
+        ));
+    };
+
+    match subcommand {
+        "set" => {
+            let (Some(level), Some(name), Some(role)) = (
+                option_int(&options, "level"),
+                option_str(&options, "name"),
+                option_role(&options, "role"),
+            ) else {
+                return Err(TicketError::Validation(
+                    "Please provide a level, a name and a role.".into(),
+                ));
+            };
+            let tier = EscalationTier {
+                level,
+                name: name.to_string(),
+                category_id: option_category(ctx, guild_id, &options, "category").await?,
+                role_id: role.id.get() as i64,
+            };
+            config.set_escalation_tier(guild_id, &tier).await?;
+            Ok(format!("Escalation tier {} is now {}.", level, name))
+        }
+        "remove" => {
+            let Some(level) = option_int(&options, "level") else {
+                return Err(TicketError::Validation("Please provide a level.".into()));
+            };
+            if config.remove_escalation_tier(guild_id, level).await? {
+                Ok(format!("Removed escalation tier {}.", level))
+            } else {
+                Ok(format!("There is no escalation tier {}.", level))
+            }
+        }
+        "list" => {
+            let tiers = config.get_escalation_tiers(guild_id).await?;
+            if tiers.is_empty() {
+                return Ok("No escalation tiers are configured.".to_string());
+            }
+
+            let lines = tiers
+                .iter()
+                .map(|tier| {
+                    format!(
+                        "{}. **{}**: {}, category {}",
+                        tier.level,
+                        tier.name,
+                        tier.role_id().mention(),
+                        tier.category_id()
+                            .map(|id| id.mention().to_string())
+                            .unwrap_or_else(|| "unchanged".to_string())
+                    )
+                })
+                .collect::<Vec<_>>();
+            Ok(lines.join("\n"))
+        }
+        _ => Err(TicketError::Validation("Unknown subcommand.".into())),
+    }
+}
+
+pub async fn ticket_idle(
+    command: &CommandInteraction,
+    config: &Arc<Config>,
+) -> Result<String, TicketError> {
+    let guild_id = guild_of(command)?;
+    let options = leaf_options(command);
+
+    if let Some(idle) = option_int(&options, "idle") {
+        config.set_idle_close_hours(guild_id, idle).await?;
+        // Start the countdown of tickets that are already open too, from
+        // their last message.
+        match config.get_idle_close_hours(guild_id).await? {
+            Some(hours) => {
+                config
+                    .tickets
+                    .schedule_idle_warnings(
+                        guild_id,
+                        Timestamp::now().unix_timestamp(),
+                        hours * 3600,
+                    )
+                    .await?
+            }
+            None => config.tickets.cancel_idle_jobs(guild_id).await?,
+        }
+    }
+    if let Some(grace) = option_int(&options, "grace") {
+        config.set_idle_grace_hours(guild_id, grace).await?;
+    }
+
+    let Some(idle) = config.get_idle_close_hours(guild_id).await? else {
+        return Ok("Inactive tickets are never closed automatically.".to_string());
+    };
+    let grace = config.get_idle_grace_hours(guild_id).await?;
+    Ok(format!(
+        "Tickets get a warning after {} hour(s) without messages and are closed {} hour(s) \
+        later.",
+        idle, grace
+    ))
+}
+
+pub async fn ticket_close_delay(
+    command: &CommandInteraction,
+    config: &Arc<Config>,
+) -> Result<String, TicketError> {
+    let guild_id = guild_of(command)?;
+    let options = leaf_options(command);
+
+    if let Some(delay) = option_str(&options, "delay") {
+        let delay = parse_duration(delay)?;
+        if delay > MAX_CLOSE_DELAY_SECS {
+            return Err(TicketError::Validation(
+                "The close delay can be at most 7 days.".into(),
+            ));
+        }
+        config.set_close_delay(guild_id, delay).await?;
+    }
+
+    let delay = config.get_close_delay(guild_id).await?;
+    Ok(format!(
+        "Tickets are closed {} second(s) after `/ticket close` unless somebody cancels.",
+        delay
+    ))
+}
+
+/// Cancels the scheduled close `job_id` from the button on the close message.
+pub async fn cancel_close(
+    ctx: &Context,
+    component: &ComponentInteraction,
+    job_id: Option<i64>,
+    config: &Arc<Config>,
+) -> Result<String, TicketError> {
+    let cancelled = match job_id {
+        Some(job_id) => config.tickets.cancel_job_by_id(job_id).await?.is_some(),
+        None => true,
+    };
+
+    if cancelled {
+        if let Some(ticket) = config
+            .tickets
+            .find_by_channel(component.channel_id)
+            .await?
+            .filter(|ticket| ticket.status == TicketStatus::PendingClose)
+        {
+            config
+                .tickets
+                .set_status(&ticket, TicketStatus::Open)
+                .await?;
+            config
+                .tickets
+                .record_event(
+                    &ticket,
+                    TicketEventKind::CloseCancelled,
+                    component.user.id,
+                    None,
+                    json!({}),
+                )
+                .await?;
+            if let Some(guild_id) = component.guild_id {
+                scheduler::record_activity(guild_id, component.channel_id, config).await?;
+            }
+        }
+    }
+
+    let mut message = component.message.clone();
+    message
+        .edit(&ctx.http, EditMessage::new().components(vec![]))
+        .await?;
+
+    Ok(if cancelled {
+        "Ticket closure cancelled.".to_string()
     } else {
-        Err(TicketError(Cow::Borrowed("This command can only be used in a server.")).into())
+        "This ticket was already closed or the closure was cancelled.".to_string()
+    })
+}
+
+pub async fn reopen_ticket(
+    ctx: &Context,
+    component: &ComponentInteraction,
+    config: &Arc<Config>,
+) -> Result<String, TicketError> {
+    let content = reopen(ctx, component, config).await?;
+
+    let mut message = component.message.clone();
+    message
+        .edit(&ctx.http, EditMessage::new().components(vec![]))
+        .await?;
+
+    Ok(content)
+}
+
+/// The channel `channel_id` of `guild_id`, provided it is one of `kinds`.
+pub async fn guild_channel_of_kind(
+    ctx: &Context,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    kinds: &[ChannelType],
+    what: &str,
+) -> Result<GuildChannel, TicketError> {
+    match channel_id.to_channel(ctx).await? {
+        Channel::Guild(channel)
+            if channel.guild_id == guild_id && kinds.contains(&channel.kind) =>
+        {
+            Ok(channel)
+        }
+        _ => Err(TicketError::Validation(
+            format!("{} is not {} in this server.", channel_id.mention(), what).into(),
+        )),
+    }
+}
+
+/// Sets the category new tickets are created in.
+pub async fn set_category(
+    ctx: &Context,
+    command: &CommandInteraction,
+    config: &Arc<Config>,
+) -> Result<String, TicketError> {
+    let guild_id = guild_of(command)?;
+    let Some(category) = option_channel(&leaf_options(command), "category") else {
+        return Err(TicketError::Validation("Please pick a category.".into()));
+    };
+
+    let category = save_category(ctx, guild_id, category.id, config).await?;
+    Ok(format!(
+        "New tickets will be created in {}.",
+        category.mention()
+    ))
+}
+
+/// Makes `channel_id` the ticket category of `guild_id`, provided it is a
+/// category the bot can create ticket channels in.
+pub async fn save_category(
+    ctx: &Context,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    config: &Arc<Config>,
+) -> Result<GuildChannel, TicketError> {
+    let channel = ticket_category(ctx, guild_id, channel_id).await?;
+    config
+        .set_category_id(guild_id, channel.id.get() as i64)
+        .await?;
+    Ok(channel)
+}
+
+/// The category `channel_id` of `guild_id`, provided the bot can keep ticket
+/// channels in it.
+async fn ticket_category(
+    ctx: &Context,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+) -> Result<GuildChannel, TicketError> {
+    let channel = guild_channel_of_kind(
+        ctx,
+        guild_id,
+        channel_id,
+        &[ChannelType::Category],
+        "a category",
+    )
+    .await?;
+    require_bot_permissions(ctx, &channel, CATEGORY_PERMISSIONS).await?;
+    Ok(channel)
+}
+
+/// Sets the channel ticket actions and transcripts are logged in.
+pub async fn set_log_channel(
+    ctx: &Context,
+    command: &CommandInteraction,
+    config: &Arc<Config>,
+) -> Result<String, TicketError> {
+    let guild_id = guild_of(command)?;
+    let Some(channel) = option_channel(&leaf_options(command), "channel") else {
+        return Err(TicketError::Validation("Please pick a channel.".into()));
+    };
+
+    let channel = save_log_channel(ctx, guild_id, channel.id, config).await?;
+    Ok(format!(
+        "Ticket actions will be logged in {}.",
+        channel.mention()
+    ))
+}
+
+/// Makes `channel_id` the log channel of `guild_id`, provided it is a text
+/// channel the bot can post logs and transcripts in.
+pub async fn save_log_channel(
+    ctx: &Context,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    config: &Arc<Config>,
+) -> Result<GuildChannel, TicketError> {
+    let channel = guild_channel_of_kind(
+        ctx,
+        guild_id,
+        channel_id,
+        &[ChannelType::Text, ChannelType::News],
+        "a text channel",
+    )
+    .await?;
+    require_bot_permissions(ctx, &channel, LOG_CHANNEL_PERMISSIONS).await?;
+    config
+        .set_log_channel_id(guild_id, channel.id.get() as i64)
+        .await?;
+    Ok(channel)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_duration_accepts_units_and_plain_seconds() {
+        assert_eq!(parse_duration("1d12h").unwrap(), 129_600);
+        assert_eq!(parse_duration("90").unwrap(), 90);
+        assert_eq!(parse_duration("10m30s").unwrap(), 630);
+        assert_eq!(parse_duration(" 2H ").unwrap(), 7200);
+    }
+
+    #[test]
+    fn parse_duration_rejects_garbage() {
+        for input in ["-5", "5x", "", "h", "1h30", "1.5h"] {
+            assert!(
+                parse_duration(input).is_err(),
+                "{:?} should be rejected",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn parse_duration_rejects_overflow() {
+        assert!(parse_duration("9223372036854775807d").is_err());
+        assert!(parse_duration("99999999999999999999s").is_err());
+        assert!(parse_duration("9223372036854775807s1s").is_err());
+    }
+
+    fn user(name: &str) -> User {
+        let mut user = User::default();
+        user.id = UserId::new(1234);
+        user.name = name.to_string();
+        user
+    }
+
+    fn ticket_type(name: &str) -> TicketType {
+        TicketType {
+            id: 1,
+            name: name.to_string(),
+            emoji: None,
+            category_id: None,
+            welcome_message: None,
+            channel_name_template: None,
+            max_open_tickets: None,
+            require_close_reason: false,
+            close_mode: None,
+        }
+    }
+
+    #[test]
+    fn sanitize_channel_name_follows_discord_rules() {
+        assert_eq!(sanitize_channel_name("Billing Help"), "billing-help");
+        assert_eq!(sanitize_channel_name("  a  --  b  "), "a-b");
+        assert_eq!(
+            sanitize_channel_name("Ticket #1: urgent!"),
+            "ticket-1-urgent"
+        );
+        assert_eq!(sanitize_channel_name("ÜBER_straße"), "über_straße");
+        assert_eq!(sanitize_channel_name("日本語 チケット"), "日本語-チケット");
+        assert_eq!(sanitize_channel_name("🎫 support 🎫"), "support");
+        assert_eq!(sanitize_channel_name("!!!"), "");
+    }
+
+    #[test]
+    fn sanitize_channel_name_caps_length() {
+        let name = sanitize_channel_name(&"é".repeat(150));
+        assert_eq!(name.chars().count(), 100);
+
+        let name = sanitize_channel_name(&format!("{} tail", "a".repeat(99)));
+        assert_eq!(name, "a".repeat(99));
+    }
+
+    #[test]
+    fn render_channel_name_fills_placeholders() {
+        assert_eq!(
+            render_channel_name("ticket-{number}", 7, &user("Alice"), None),
+            "ticket-0007"
+        );
+        assert_eq!(
+            render_channel_name(
+                "{type} {user} {userid}",
+                12,
+                &user("Bob Smith"),
+                Some(&ticket_type("Report User"))
+            ),
+            "report-user-bob-smith-1234"
+        );
+        assert_eq!(
+            render_channel_name("{type}-{number}", 3, &user("Alice"), None),
+            "ticket-0003"
+        );
+    }
+
+    #[test]
+    fn render_channel_name_falls_back_when_empty() {
+        assert_eq!(
+            render_channel_name("{user}", 42, &user("★★★"), None),
+            "ticket-0042"
+        );
+        assert_eq!(
+            render_channel_name("!!!", 5, &user("Alice"), None),
+            "ticket-0005"
+        );
     }
 }
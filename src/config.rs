@@ -1,10 +1,130 @@
+use crate::tickets::TicketService;
+use serenity::all::{ChannelId, GuildId, RoleId};
 use sqlx::SqlitePool;
 use std::env;
+use std::num::NonZeroU64;
 use std::sync::Arc;
 
+/// A kind of ticket members can open from the panel, e.g. "Report user" or
+/// "Billing". Unset fields fall back to the guild-wide configuration.
+#[derive(Debug, Clone)]
+pub struct TicketType {
+    pub id: i64,
+    pub name: String,
+    pub emoji: Option<String>,
+    pub category_id: Option<i64>,
+    pub welcome_message: Option<String>,
+    pub channel_name_template: Option<String>,
+    pub max_open_tickets: Option<i64>,
+    pub require_close_reason: bool,
+    pub close_mode: Option<CloseMode>,
+}
+
+impl TicketType {
+    pub fn category_id(&self) -> Option<ChannelId> {
+        self.category_id.map(|id| ChannelId::new(id as u64))
+    }
+}
+
+#[derive(Default)]
+pub struct TicketTypeFields<'a> {
+    pub emoji: Option<&'a str>,
+    pub category_id: Option<i64>,
+    pub welcome_message: Option<&'a str>,
+    pub channel_name_template: Option<&'a str>,
+    /// `Some(0)` clears the limit so the guild-wide one applies again.
+    pub max_open_tickets: Option<i64>,
+    pub require_close_reason: Option<bool>,
+    pub close_mode: Option<CloseMode>,
+}
+
+/// What happens to a ticket channel once the ticket is closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(rename_all = "snake_case")]
+pub enum CloseMode {
+    /// The channel is deleted right away.
+    Delete,
+    /// The channel is hidden from the opener, renamed and kept for a while so
+    /// staff can reopen the ticket.
+    Archive,
+}
+
+/// Seconds between asking to close a ticket and it being closed, when the
+/// guild has not set a delay.
+pub const DEFAULT_CLOSE_DELAY_SECS: i64 = 5;
+
+/// Hours an inactive ticket gets after the warning before it is closed, when
+/// the guild has not set a grace period.
+pub const DEFAULT_IDLE_GRACE_HOURS: i64 = 24;
+
+/// Hours an archived ticket channel is kept when the guild has not set a
+/// retention period.
+pub const DEFAULT_ARCHIVE_RETENTION_HOURS: i64 = 168;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(rename_all = "snake_case")]
+pub enum QuestionStyle {
+    Short,
+    Paragraph,
+}
+
+/// A question asked in the modal shown before a ticket of a type is opened.
+#[derive(Debug, Clone)]
+pub struct TicketQuestion {
+    pub id: i64,
+    pub position: i64,
+    pub label: String,
+    pub style: QuestionStyle,
+    pub required: bool,
+    pub placeholder: Option<String>,
+}
+
+/// A step on the escalation ladder. Escalated tickets move into the tier's
+/// category and are staffed by its role instead of the usual staff roles.
+#[derive(Debug, Clone)]
+pub struct EscalationTier {
+    pub level: i64,
+    pub name: String,
+    pub category_id: Option<i64>,
+    pub role_id: i64,
+}
+
+impl EscalationTier {
+    pub fn category_id(&self) -> Option<ChannelId> {
+        self.category_id.map(|id| ChannelId::new(id as u64))
+    }
+
+    pub fn role_id(&self) -> RoleId {
+        RoleId::new(self.role_id as u64)
+    }
+}
+
+/// The category and log channel from before settings were kept per guild,
+/// when the bot served a single server.
+#[derive(Debug, Clone, Copy)]
+pub struct LegacyConfig {
+    pub category_id: Option<i64>,
+    pub log_channel_id: Option<i64>,
+}
+
+impl LegacyConfig {
+    /// The configured channels, category first, to find out which guild the
+    /// settings belong to.
+    pub fn channel_ids(&self) -> impl Iterator<Item = ChannelId> {
+        [self.category_id, self.log_channel_id]
+            .into_iter()
+            .flatten()
+            .map(|id| ChannelId::new(id as u64))
+    }
+}
+
 pub struct Config {
     pub token: String,
+    /// Guild to register slash commands in instead of globally, from
+    /// `COMMAND_GUILD_ID`.
+    pub command_guild_id: Option<GuildId>,
     pub db: Arc<SqlitePool>,
+    pub tickets: TicketService,
 }
 
 impl Config {
@@ -14,56 +134,790 @@ impl Config {
         let database_url = format!("sqlite:{}", db_path.display());
         let pool = SqlitePool::connect(&database_url).await?;
 
-        sqlx::query(
-            "CREATE TABLE IF NOT EXISTS config (
-                key TEXT PRIMARY KEY,
-                category_id INTEGER,
-                log_channel_id INTEGER
-            )",
-        )
-        .execute(&pool)
-        .await?;
-
-        sqlx::query("INSERT OR IGNORE INTO config (key) VALUES ('main')")
-            .execute(&pool)
-            .await?;
+        let mut migrator = sqlx::migrate!();
+        migrator.set_ignore_missing(true);
+        migrator.run(&pool).await?;
 
         let db = Arc::new(pool);
 
         Ok(Self {
             token: env::var("DISCORD_TOKEN").expect("DISCORD_TOKEN must be set"),
+            command_guild_id: match env::var("COMMAND_GUILD_ID") {
+                Ok(id) => Some(GuildId::from(id.parse::<NonZeroU64>()?)),
+                Err(_) => None,
+            },
+            tickets: TicketService::new(Arc::clone(&db)),
             db,
         })
     }
 
-    pub async fn get_category_id(&self) -> Result<Option<i64>, sqlx::Error> {
-        sqlx::query_scalar!("SELECT category_id FROM config WHERE key = 'main'")
-            .fetch_optional(&*self.db)
-            .await
-            .map(|opt| opt.flatten())
+    pub async fn get_category_id(&self, guild_id: GuildId) -> Result<Option<i64>, sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        sqlx::query_scalar!(
+            "SELECT category_id FROM guild_configs WHERE guild_id = ?",
+            guild_id
+        )
+        .fetch_optional(&*self.db)
+        .await
+        .map(|opt| opt.flatten())
     }
 
-    pub async fn set_category_id(&self, id: i64) -> Result<(), sqlx::Error> {
-        sqlx::query!("UPDATE config SET category_id = ? WHERE key = 'main'", id)
-            .execute(&*self.db)
+    pub async fn set_category_id(&self, guild_id: GuildId, id: i64) -> Result<(), sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        sqlx::query!(
+            "INSERT INTO guild_configs (guild_id, category_id) VALUES (?, ?)
+            ON CONFLICT (guild_id) DO UPDATE SET category_id = excluded.category_id",
+            guild_id,
+            id
+        )
+        .execute(&*self.db)
+        .await?;
+        Ok(())
+    }
+
+    /// The settings left in the old single-server `config` table, if there
+    /// are any that were not carried over yet.
+    pub async fn get_legacy_config(&self) -> Result<Option<LegacyConfig>, sqlx::Error> {
+        sqlx::query_as!(
+            LegacyConfig,
+            "SELECT category_id, log_channel_id FROM config
+            WHERE key = 'main' AND (category_id IS NOT NULL OR log_channel_id IS NOT NULL)"
+        )
+        .fetch_optional(&*self.db)
+        .await
+    }
+
+    /// Moves the old single-server settings to `guild_id`, unless the guild
+    /// already has its own, and clears them so this only happens once.
+    /// `None` drops them without moving them anywhere.
+    pub async fn carry_over_legacy_config(
+        &self,
+        guild_id: Option<GuildId>,
+        legacy: &LegacyConfig,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.db.begin().await?;
+        if let Some(guild_id) = guild_id {
+            let guild_id = guild_id.get() as i64;
+            sqlx::query!(
+                "INSERT INTO guild_configs (guild_id, category_id, log_channel_id) VALUES (?, ?, ?)
+                ON CONFLICT (guild_id) DO UPDATE SET
+                    category_id = COALESCE(category_id, excluded.category_id),
+                    log_channel_id = COALESCE(log_channel_id, excluded.log_channel_id)",
+                guild_id,
+                legacy.category_id,
+                legacy.log_channel_id
+            )
+            .execute(&mut *tx)
             .await?;
+        }
+        sqlx::query!(
+            "UPDATE config SET category_id = NULL, log_channel_id = NULL WHERE key = 'main'"
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await
+    }
+
+    pub async fn get_log_channel_id(&self, guild_id: GuildId) -> Result<Option<i64>, sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        sqlx::query_scalar!(
+            "SELECT log_channel_id FROM guild_configs WHERE guild_id = ?",
+            guild_id
+        )
+        .fetch_optional(&*self.db)
+        .await
+        .map(|opt| opt.flatten())
+    }
+
+    pub async fn set_log_channel_id(&self, guild_id: GuildId, id: i64) -> Result<(), sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        sqlx::query!(
+            "INSERT INTO guild_configs (guild_id, log_channel_id) VALUES (?, ?)
+            ON CONFLICT (guild_id) DO UPDATE SET log_channel_id = excluded.log_channel_id",
+            guild_id,
+            id
+        )
+        .execute(&*self.db)
+        .await?;
+        Ok(())
+    }
+
+    /// How many tickets a member may have open at once, across all types.
+    pub async fn get_max_open_tickets(&self, guild_id: GuildId) -> Result<i64, sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        sqlx::query_scalar!(
+            "SELECT max_open_tickets FROM guild_configs WHERE guild_id = ?",
+            guild_id
+        )
+        .fetch_optional(&*self.db)
+        .await
+        .map(|opt| opt.flatten().unwrap_or(1))
+    }
+
+    pub async fn set_max_open_tickets(
+        &self,
+        guild_id: GuildId,
+        max: i64,
+    ) -> Result<(), sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        sqlx::query!(
+            "INSERT INTO guild_configs (guild_id, max_open_tickets) VALUES (?, ?)
+            ON CONFLICT (guild_id) DO UPDATE SET max_open_tickets = excluded.max_open_tickets",
+            guild_id,
+            max
+        )
+        .execute(&*self.db)
+        .await?;
+        Ok(())
+    }
+
+    /// Seconds a member has to wait after opening a ticket before opening
+    /// another one.
+    pub async fn get_ticket_cooldown(&self, guild_id: GuildId) -> Result<i64, sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        sqlx::query_scalar!(
+            "SELECT ticket_cooldown_secs FROM guild_configs WHERE guild_id = ?",
+            guild_id
+        )
+        .fetch_optional(&*self.db)
+        .await
+        .map(|opt| opt.flatten().unwrap_or(0))
+    }
+
+    pub async fn set_ticket_cooldown(
+        &self,
+        guild_id: GuildId,
+        secs: i64,
+    ) -> Result<(), sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        sqlx::query!(
+            "INSERT INTO guild_configs (guild_id, ticket_cooldown_secs) VALUES (?, ?)
+            ON CONFLICT (guild_id) DO UPDATE SET ticket_cooldown_secs = excluded.ticket_cooldown_secs",
+            guild_id,
+            secs
+        )
+        .execute(&*self.db)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_channel_name_template(
+        &self,
+        guild_id: GuildId,
+    ) -> Result<Option<String>, sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        sqlx::query_scalar!(
+            "SELECT channel_name_template FROM guild_configs WHERE guild_id = ?",
+            guild_id
+        )
+        .fetch_optional(&*self.db)
+        .await
+        .map(|opt| opt.flatten())
+    }
+
+    pub async fn set_channel_name_template(
+        &self,
+        guild_id: GuildId,
+        template: &str,
+    ) -> Result<(), sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        sqlx::query!(
+            "INSERT INTO guild_configs (guild_id, channel_name_template) VALUES (?, ?)
+            ON CONFLICT (guild_id) DO UPDATE SET channel_name_template = excluded.channel_name_template",
+            guild_id,
+            template
+        )
+        .execute(&*self.db)
+        .await?;
+        Ok(())
+    }
+
+    /// Close mode of tickets whose type does not set one.
+    pub async fn get_close_mode(&self, guild_id: GuildId) -> Result<CloseMode, sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        sqlx::query_scalar!(
+            r#"SELECT close_mode AS "close_mode: CloseMode" FROM guild_configs WHERE guild_id = ?"#,
+            guild_id
+        )
+        .fetch_optional(&*self.db)
+        .await
+        .map(|opt| opt.flatten().unwrap_or(CloseMode::Delete))
+    }
+
+    pub async fn set_close_mode(
+        &self,
+        guild_id: GuildId,
+        mode: CloseMode,
+    ) -> Result<(), sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        sqlx::query!(
+            "INSERT INTO guild_configs (guild_id, close_mode) VALUES (?, ?)
+            ON CONFLICT (guild_id) DO UPDATE SET close_mode = excluded.close_mode",
+            guild_id,
+            mode
+        )
+        .execute(&*self.db)
+        .await?;
+        Ok(())
+    }
+
+    /// Category archived ticket channels are moved into. Without one they
+    /// stay where they are.
+    pub async fn get_archive_category_id(
+        &self,
+        guild_id: GuildId,
+    ) -> Result<Option<i64>, sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        sqlx::query_scalar!(
+            "SELECT archive_category_id FROM guild_configs WHERE guild_id = ?",
+            guild_id
+        )
+        .fetch_optional(&*self.db)
+        .await
+        .map(|opt| opt.flatten())
+    }
+
+    pub async fn set_archive_category_id(
+        &self,
+        guild_id: GuildId,
+        id: i64,
+    ) -> Result<(), sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        sqlx::query!(
+            "INSERT INTO guild_configs (guild_id, archive_category_id) VALUES (?, ?)
+            ON CONFLICT (guild_id) DO UPDATE SET archive_category_id = excluded.archive_category_id",
+            guild_id,
+            id
+        )
+        .execute(&*self.db)
+        .await?;
+        Ok(())
+    }
+
+    /// Hours archived ticket channels are kept before they are deleted, 0 to
+    /// keep them forever.
+    pub async fn get_archive_retention_hours(&self, guild_id: GuildId) -> Result<i64, sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        sqlx::query_scalar!(
+            "SELECT archive_retention_hours FROM guild_configs WHERE guild_id = ?",
+            guild_id
+        )
+        .fetch_optional(&*self.db)
+        .await
+        .map(|opt| opt.flatten().unwrap_or(DEFAULT_ARCHIVE_RETENTION_HOURS))
+    }
+
+    pub async fn set_archive_retention_hours(
+        &self,
+        guild_id: GuildId,
+        hours: i64,
+    ) -> Result<(), sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        sqlx::query!(
+            "INSERT INTO guild_configs (guild_id, archive_retention_hours) VALUES (?, ?)
+            ON CONFLICT (guild_id) DO UPDATE SET archive_retention_hours = excluded.archive_retention_hours",
+            guild_id,
+            hours
+        )
+        .execute(&*self.db)
+        .await?;
+        Ok(())
+    }
+
+    /// Whether claiming a ticket leaves the other staff members read-only.
+    pub async fn get_claim_read_only(&self, guild_id: GuildId) -> Result<bool, sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        sqlx::query_scalar!(
+            "SELECT claim_read_only FROM guild_configs WHERE guild_id = ?",
+            guild_id
+        )
+        .fetch_optional(&*self.db)
+        .await
+        .map(|opt| opt.unwrap_or(false))
+    }
+
+    pub async fn set_claim_read_only(
+        &self,
+        guild_id: GuildId,
+        read_only: bool,
+    ) -> Result<(), sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        sqlx::query!(
+            "INSERT INTO guild_configs (guild_id, claim_read_only) VALUES (?, ?)
+            ON CONFLICT (guild_id) DO UPDATE SET claim_read_only = excluded.claim_read_only",
+            guild_id,
+            read_only
+        )
+        .execute(&*self.db)
+        .await?;
+        Ok(())
+    }
+
+    /// Seconds closing a ticket waits for somebody to cancel.
+    pub async fn get_close_delay(&self, guild_id: GuildId) -> Result<i64, sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        sqlx::query_scalar!(
+            "SELECT close_delay_secs FROM guild_configs WHERE guild_id = ?",
+            guild_id
+        )
+        .fetch_optional(&*self.db)
+        .await
+        .map(|opt| opt.flatten().unwrap_or(DEFAULT_CLOSE_DELAY_SECS))
+    }
+
+    pub async fn set_close_delay(&self, guild_id: GuildId, secs: i64) -> Result<(), sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        sqlx::query!(
+            "INSERT INTO guild_configs (guild_id, close_delay_secs) VALUES (?, ?)
+            ON CONFLICT (guild_id) DO UPDATE SET close_delay_secs = excluded.close_delay_secs",
+            guild_id,
+            secs
+        )
+        .execute(&*self.db)
+        .await?;
+        Ok(())
+    }
+
+    /// Hours without messages after which a ticket gets an inactivity warning,
+    /// `None` when inactive tickets are never closed.
+    pub async fn get_idle_close_hours(
+        &self,
+        guild_id: GuildId,
+    ) -> Result<Option<i64>, sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        sqlx::query_scalar!(
+            "SELECT idle_close_hours FROM guild_configs WHERE guild_id = ?",
+            guild_id
+        )
+        .fetch_optional(&*self.db)
+        .await
+        .map(|opt| opt.flatten().filter(|hours| *hours > 0))
+    }
+
+    pub async fn set_idle_close_hours(
+        &self,
+        guild_id: GuildId,
+        hours: i64,
+    ) -> Result<(), sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        sqlx::query!(
+            "INSERT INTO guild_configs (guild_id, idle_close_hours) VALUES (?, ?)
+            ON CONFLICT (guild_id) DO UPDATE SET idle_close_hours = excluded.idle_close_hours",
+            guild_id,
+            hours
+        )
+        .execute(&*self.db)
+        .await?;
+        Ok(())
+    }
+
+    /// Hours between the inactivity warning and the ticket being closed.
+    pub async fn get_idle_grace_hours(&self, guild_id: GuildId) -> Result<i64, sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        sqlx::query_scalar!(
+            "SELECT idle_grace_hours FROM guild_configs WHERE guild_id = ?",
+            guild_id
+        )
+        .fetch_optional(&*self.db)
+        .await
+        .map(|opt| opt.flatten().unwrap_or(DEFAULT_IDLE_GRACE_HOURS))
+    }
+
+    pub async fn set_idle_grace_hours(
+        &self,
+        guild_id: GuildId,
+        hours: i64,
+    ) -> Result<(), sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        sqlx::query!(
+            "INSERT INTO guild_configs (guild_id, idle_grace_hours) VALUES (?, ?)
+            ON CONFLICT (guild_id) DO UPDATE SET idle_grace_hours = excluded.idle_grace_hours",
+            guild_id,
+            hours
+        )
+        .execute(&*self.db)
+        .await?;
         Ok(())
     }
 
-    pub async fn get_log_channel_id(&self) -> Result<Option<i64>, sqlx::Error> {
-        sqlx::query_scalar!("SELECT log_channel_id FROM config WHERE key = 'main'")
-            .fetch_optional(&*self.db)
-            .await
-            .map(|opt| opt.flatten())
+    /// Bumps the guild's ticket counter and returns the new ticket number.
+    pub async fn next_ticket_number(&self, guild_id: GuildId) -> Result<i64, sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        sqlx::query_scalar!(
+            "INSERT INTO guild_configs (guild_id, ticket_counter) VALUES (?, 1)
+            ON CONFLICT (guild_id) DO UPDATE SET ticket_counter = ticket_counter + 1
+            RETURNING ticket_counter",
+            guild_id
+        )
+        .fetch_one(&*self.db)
+        .await
+    }
+
+    pub async fn get_support_roles(&self, guild_id: GuildId) -> Result<Vec<RoleId>, sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        let roles = sqlx::query_scalar!(
+            "SELECT role_id FROM support_roles WHERE guild_id = ? ORDER BY role_id",
+            guild_id
+        )
+        .fetch_all(&*self.db)
+        .await?;
+        Ok(roles.into_iter().map(|id| RoleId::new(id as u64)).collect())
+    }
+
+    pub async fn add_support_role(
+        &self,
+        guild_id: GuildId,
+        role_id: RoleId,
+    ) -> Result<bool, sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        let role_id = role_id.get() as i64;
+        let result = sqlx::query!(
+            "INSERT OR IGNORE INTO support_roles (guild_id, role_id) VALUES (?, ?)",
+            guild_id,
+            role_id
+        )
+        .execute(&*self.db)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn remove_support_role(
+        &self,
+        guild_id: GuildId,
+        role_id: RoleId,
+    ) -> Result<bool, sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        let role_id = role_id.get() as i64;
+        let result = sqlx::query!(
+            "DELETE FROM support_roles WHERE guild_id = ? AND role_id = ?",
+            guild_id,
+            role_id
+        )
+        .execute(&*self.db)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn get_ticket_types(
+        &self,
+        guild_id: GuildId,
+    ) -> Result<Vec<TicketType>, sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        sqlx::query_as!(
+            TicketType,
+            r#"SELECT id AS "id!", name, emoji, category_id, welcome_message, channel_name_template,
+                max_open_tickets, require_close_reason, close_mode AS "close_mode: CloseMode"
+            FROM ticket_types WHERE guild_id = ? ORDER BY id"#,
+            guild_id
+        )
+        .fetch_all(&*self.db)
+        .await
+    }
+
+    pub async fn get_ticket_type(
+        &self,
+        guild_id: GuildId,
+        id: i64,
+    ) -> Result<Option<TicketType>, sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        sqlx::query_as!(
+            TicketType,
+            r#"SELECT id AS "id!", name, emoji, category_id, welcome_message, channel_name_template,
+                max_open_tickets, require_close_reason, close_mode AS "close_mode: CloseMode"
+            FROM ticket_types WHERE guild_id = ? AND id = ?"#,
+            guild_id,
+            id
+        )
+        .fetch_optional(&*self.db)
+        .await
+    }
+
+    pub async fn find_ticket_type(
+        &self,
+        guild_id: GuildId,
+        name: &str,
+    ) -> Result<Option<TicketType>, sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        sqlx::query_as!(
+            TicketType,
+            r#"SELECT id AS "id!", name, emoji, category_id, welcome_message, channel_name_template,
+                max_open_tickets, require_close_reason, close_mode AS "close_mode: CloseMode"
+            FROM ticket_types WHERE guild_id = ? AND name = ? COLLATE NOCASE"#,
+            guild_id,
+            name
+        )
+        .fetch_optional(&*self.db)
+        .await
+    }
+
+    pub async fn create_ticket_type(
+        &self,
+        guild_id: GuildId,
+        name: &str,
+        fields: &TicketTypeFields<'_>,
+    ) -> Result<TicketType, sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        sqlx::query_as!(
+            TicketType,
+            r#"INSERT INTO ticket_types
+                (guild_id, name, emoji, category_id, welcome_message, channel_name_template,
+                max_open_tickets, require_close_reason, close_mode)
+            VALUES (?, ?, ?, ?, ?, ?, NULLIF(?, 0), COALESCE(?, FALSE), ?)
+            RETURNING id, name, emoji, category_id, welcome_message, channel_name_template,
+                max_open_tickets, require_close_reason, close_mode AS "close_mode: CloseMode""#,
+            guild_id,
+            name,
+            fields.emoji,
+            fields.category_id,
+            fields.welcome_message,
+            fields.channel_name_template,
+            fields.max_open_tickets,
+            fields.require_close_reason,
+            fields.close_mode
+        )
+        .fetch_one(&*self.db)
+        .await
     }
 
-    pub async fn set_log_channel_id(&self, id: i64) -> Result<(), sqlx::Error> {
+    /// Overwrites the fields that are set in `fields` and keeps the others.
+    pub async fn update_ticket_type(
+        &self,
+        id: i64,
+        fields: &TicketTypeFields<'_>,
+    ) -> Result<(), sqlx::Error> {
         sqlx::query!(
-            "UPDATE config SET log_channel_id = ? WHERE key = 'main'",
+            "UPDATE ticket_types SET
+                emoji = COALESCE(?, emoji),
+                category_id = COALESCE(?, category_id),
+                welcome_message = COALESCE(?, welcome_message),
+                channel_name_template = COALESCE(?, channel_name_template),
+                max_open_tickets = NULLIF(COALESCE(?, max_open_tickets), 0),
+                require_close_reason = COALESCE(?, require_close_reason),
+                close_mode = COALESCE(?, close_mode)
+            WHERE id = ?",
+            fields.emoji,
+            fields.category_id,
+            fields.welcome_message,
+            fields.channel_name_template,
+            fields.max_open_tickets,
+            fields.require_close_reason,
+            fields.close_mode,
             id
         )
         .execute(&*self.db)
         .await?;
         Ok(())
     }
+
+    pub async fn delete_ticket_type(&self, id: i64) -> Result<(), sqlx::Error> {
+        sqlx::query!("DELETE FROM ticket_types WHERE id = ?", id)
+            .execute(&*self.db)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get_ticket_type_roles(&self, type_id: i64) -> Result<Vec<RoleId>, sqlx::Error> {
+        let roles = sqlx::query_scalar!(
+            "SELECT role_id FROM ticket_type_roles WHERE type_id = ? ORDER BY role_id",
+            type_id
+        )
+        .fetch_all(&*self.db)
+        .await?;
+        Ok(roles.into_iter().map(|id| RoleId::new(id as u64)).collect())
+    }
+
+    pub async fn add_ticket_type_role(
+        &self,
+        type_id: i64,
+        role_id: RoleId,
+    ) -> Result<bool, sqlx::Error> {
+        let role_id = role_id.get() as i64;
+        let result = sqlx::query!(
+            "INSERT OR IGNORE INTO ticket_type_roles (type_id, role_id) VALUES (?, ?)",
+            type_id,
+            role_id
+        )
+        .execute(&*self.db)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn remove_ticket_type_role(
+        &self,
+        type_id: i64,
+        role_id: RoleId,
+    ) -> Result<bool, sqlx::Error> {
+        let role_id = role_id.get() as i64;
+        let result = sqlx::query!(
+            "DELETE FROM ticket_type_roles WHERE type_id = ? AND role_id = ?",
+            type_id,
+            role_id
+        )
+        .execute(&*self.db)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Roles that staff a ticket of the given type: the type's own roles if it
+    /// has any, otherwise the guild's support roles.
+    pub async fn get_staff_roles(
+        &self,
+        guild_id: GuildId,
+        type_id: Option<i64>,
+    ) -> Result<Vec<RoleId>, sqlx::Error> {
+        if let Some(type_id) = type_id {
+            let roles = self.get_ticket_type_roles(type_id).await?;
+            if !roles.is_empty() {
+                return Ok(roles);
+            }
+        }
+        self.get_support_roles(guild_id).await
+    }
+
+    pub async fn get_ticket_questions(
+        &self,
+        type_id: i64,
+    ) -> Result<Vec<TicketQuestion>, sqlx::Error> {
+        sqlx::query_as!(
+            TicketQuestion,
+            r#"SELECT id AS "id!", position, label, style AS "style: QuestionStyle", required,
+                placeholder
+            FROM ticket_questions WHERE type_id = ? ORDER BY position"#,
+            type_id
+        )
+        .fetch_all(&*self.db)
+        .await
+    }
+
+    pub async fn add_ticket_question(
+        &self,
+        type_id: i64,
+        label: &str,
+        style: QuestionStyle,
+        required: bool,
+        placeholder: Option<&str>,
+    ) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar!(
+            r#"INSERT INTO ticket_questions (type_id, position, label, style, required, placeholder)
+            VALUES (
+                ?,
+                (SELECT COALESCE(MAX(position), 0) + 1 FROM ticket_questions WHERE type_id = ?),
+                ?, ?, ?, ?
+            )
+            RETURNING position"#,
+            type_id,
+            type_id,
+            label,
+            style,
+            required,
+            placeholder
+        )
+        .fetch_one(&*self.db)
+        .await
+    }
+
+    pub async fn remove_ticket_question(
+        &self,
+        type_id: i64,
+        position: i64,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            "DELETE FROM ticket_questions WHERE type_id = ? AND position = ?",
+            type_id,
+            position
+        )
+        .execute(&*self.db)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn get_escalation_tiers(
+        &self,
+        guild_id: GuildId,
+    ) -> Result<Vec<EscalationTier>, sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        sqlx::query_as!(
+            EscalationTier,
+            "SELECT level, name, category_id, role_id FROM escalation_tiers
+            WHERE guild_id = ? ORDER BY level",
+            guild_id
+        )
+        .fetch_all(&*self.db)
+        .await
+    }
+
+    pub async fn get_escalation_tier(
+        &self,
+        guild_id: GuildId,
+        level: i64,
+    ) -> Result<Option<EscalationTier>, sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        sqlx::query_as!(
+            EscalationTier,
+            "SELECT level, name, category_id, role_id FROM escalation_tiers
+            WHERE guild_id = ? AND level = ?",
+            guild_id,
+            level
+        )
+        .fetch_optional(&*self.db)
+        .await
+    }
+
+    /// The lowest tier above `level`, i.e. where a ticket at `level` escalates to.
+    pub async fn next_escalation_tier(
+        &self,
+        guild_id: GuildId,
+        level: i64,
+    ) -> Result<Option<EscalationTier>, sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        sqlx::query_as!(
+            EscalationTier,
+            "SELECT level, name, category_id, role_id FROM escalation_tiers
+            WHERE guild_id = ? AND level > ? ORDER BY level LIMIT 1",
+            guild_id,
+            level
+        )
+        .fetch_optional(&*self.db)
+        .await
+    }
+
+    pub async fn set_escalation_tier(
+        &self,
+        guild_id: GuildId,
+        tier: &EscalationTier,
+    ) -> Result<(), sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        sqlx::query!(
+            "INSERT INTO escalation_tiers (guild_id, level, name, category_id, role_id)
+            VALUES (?, ?, ?, ?, ?)
+            ON CONFLICT (guild_id, level) DO UPDATE SET
+                name = excluded.name,
+                category_id = excluded.category_id,
+                role_id = excluded.role_id",
+            guild_id,
+            tier.level,
+            tier.name,
+            tier.category_id,
+            tier.role_id
+        )
+        .execute(&*self.db)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn remove_escalation_tier(
+        &self,
+        guild_id: GuildId,
+        level: i64,
+    ) -> Result<bool, sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        let result = sqlx::query!(
+            "DELETE FROM escalation_tiers WHERE guild_id = ? AND level = ?",
+            guild_id,
+            level
+        )
+        .execute(&*self.db)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
 }
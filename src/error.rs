@@ -0,0 +1,91 @@
+use serenity::{http::HttpError, prelude::SerenityError};
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt;
+
+/// Everything that can go wrong while handling a ticket interaction. The
+/// [`Display`](fmt::Display) output is the detail meant for the logs, what the
+/// member gets to see comes from [`TicketError::user_message`].
+#[derive(Debug)]
+pub enum TicketError {
+    /// A request to Discord failed.
+    Discord(Box<SerenityError>),
+    /// A query against the bot's database failed.
+    Database(sqlx::Error),
+    /// The invoking member is not allowed to do this.
+    Permission(Cow<'static, str>),
+    /// The request itself does not make sense, e.g. a missing or bad option.
+    Validation(Cow<'static, str>),
+    /// The command only works inside an open ticket channel.
+    NotATicket,
+}
+
+impl TicketError {
+    /// Message safe to show to the member, without internal details.
+    pub fn user_message(&self) -> Cow<'_, str> {
+        match self {
+            TicketError::Discord(error) if is_forbidden(error) => {
+                Cow::Borrowed("I am missing the permissions to do that in this server.")
+            }
+            TicketError::Discord(_) => {
+                Cow::Borrowed("Discord did not accept the request. Please try again in a moment.")
+            }
+            TicketError::Database(_) => {
+                Cow::Borrowed("Something went wrong on our side. Please try again later.")
+            }
+            TicketError::Permission(message) | TicketError::Validation(message) => {
+                Cow::Borrowed(message)
+            }
+            TicketError::NotATicket => Cow::Borrowed("This channel is not an open ticket."),
+        }
+    }
+
+    /// Whether this error points at a problem with the bot rather than with
+    /// what the member asked for, and so should end up in the logs.
+    pub fn is_internal(&self) -> bool {
+        matches!(self, TicketError::Discord(_) | TicketError::Database(_))
+    }
+}
+
+/// Whether Discord refused `error` because the bot lacks permissions.
+fn is_forbidden(error: &SerenityError) -> bool {
+    matches!(
+        error,
+        SerenityError::Http(HttpError::UnsuccessfulRequest(response))
+            if response.status_code.as_u16() == 403
+    )
+}
+
+impl fmt::Display for TicketError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TicketError::Discord(error) => write!(f, "Discord error: {}", error),
+            TicketError::Database(error) => write!(f, "Database error: {}", error),
+            TicketError::Permission(message) => write!(f, "Permission denied: {}", message),
+            TicketError::Validation(message) => write!(f, "Invalid request: {}", message),
+            TicketError::NotATicket => write!(f, "Not a ticket channel"),
+        }
+    }
+}
+
+impl Error for TicketError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TicketError::Discord(error) => Some(&**error),
+            TicketError::Database(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<SerenityError> for TicketError {
+    fn from(error: SerenityError) -> Self {
+        TicketError::Discord(Box::new(error))
+    }
+}
+
+impl From<sqlx::Error> for TicketError {
+    fn from(error: sqlx::Error) -> Self {
+        TicketError::Database(error)
+    }
+}
@@ -1,33 +1,53 @@
 use crate::config::Config;
+use crate::transcript::Transcript;
 use serenity::{
     all::*,
-    builder::{CreateEmbed, CreateMessage},
+    builder::{CreateAttachment, CreateEmbed, CreateMessage},
     prelude::SerenityError,
 };
 use std::sync::Arc;
+use tracing::{debug, error};
+
+async fn log_channel(guild_id: GuildId, config: &Arc<Config>) -> Option<ChannelId> {
+    match config.get_log_channel_id(guild_id).await {
+        Ok(Some(id)) => Some(ChannelId::new(id as u64)),
+        Ok(None) => {
+            debug!(guild_id = %guild_id, "Log channel not set");
+            None
+        }
+        Err(e) => {
+            error!(error = %e, "Error fetching log channel ID");
+            None
+        }
+    }
+}
 
 pub async fn log_ticket_action(
-    ctx: &Context,
+    http: &impl CacheHttp,
     action: &str,
     user: &User,
     channel: &GuildChannel,
+    reason: Option<&str>,
     config: &Arc<Config>,
 ) -> Result<(), SerenityError> {
-    let log_channel_id = match config.get_log_channel_id().await {
-        Ok(Some(id)) => id as u64,
-        Ok(None) => {
-            println!("Log channel ID not set");
-            return Ok(());
-        }
-        Err(e) => {
-            println!("Error fetching log channel ID: {}", e);
-            return Ok(());
-        }
-    };
+    let details = reason.map(|reason| ("Reason", reason));
+    log_ticket_details(http, action, user, channel, details.as_slice(), config).await
+}
 
-    let log_channel = ChannelId::new(log_channel_id);
+/// Like [`log_ticket_action`], with arbitrary extra fields on the embed.
+pub async fn log_ticket_details(
+    http: &impl CacheHttp,
+    action: &str,
+    user: &User,
+    channel: &GuildChannel,
+    details: &[(&str, &str)],
+    config: &Arc<Config>,
+) -> Result<(), SerenityError> {
+    let Some(log_channel) = log_channel(channel.guild_id, config).await else {
+        return Ok(());
+    };
 
-    let embed = CreateEmbed::new()
+    let mut embed = CreateEmbed::new()
         .title(format!("Ticket {}", action))
         .field("User", user.name.clone(), true)
         .field("Channel", channel.name.clone(), true)
@@ -36,14 +56,82 @@ pub async fn log_ticket_action(
         .color(match action {
             "Opened" => 0x00ff00,
             "Closed" => 0xff0000,
+            "Reopened" => 0x00ffff,
+            "Claimed" => 0xffd700,
+            "Escalated" => 0x8b0000,
             "User Added" => 0x0000ff,
             "User Removed" => 0xff00ff,
             _ => 0xffa500,
         });
+    for (name, value) in details {
+        embed = embed.field(*name, *value, false);
+    }
 
     log_channel
-        .send_message(&ctx.http, CreateMessage::new().embed(embed))
+        .send_message(http, CreateMessage::new().embed(embed))
         .await?;
 
     Ok(())
 }
+
+/// Upload budget for a transcript message. Servers without boosts reject
+/// larger uploads, which would lose the transcript altogether.
+const MAX_TRANSCRIPT_BYTES: usize = 8 * 1024 * 1024;
+
+/// Posts `transcript` to the log channel. The text version is cut short to
+/// fit the upload budget and the HTML version is only attached when both fit;
+/// the full transcript stays in the database either way.
+pub async fn log_transcript(
+    http: &impl CacheHttp,
+    channel: &GuildChannel,
+    transcript: &Transcript,
+    config: &Arc<Config>,
+) -> Result<(), SerenityError> {
+    let Some(log_channel) = log_channel(channel.guild_id, config).await else {
+        return Ok(());
+    };
+
+    let text = truncate_bytes(&transcript.text, MAX_TRANSCRIPT_BYTES);
+    let with_html = text.len() + transcript.html.len() <= MAX_TRANSCRIPT_BYTES;
+
+    let mut content = format!(
+        "Transcript of #{} ({} messages)",
+        channel.name, transcript.message_count
+    );
+    if text.len() < transcript.text.len() {
+        content.push_str(
+            "\nThe transcript is too large to upload in full, the text version is cut short.",
+        );
+    } else if !with_html {
+        content.push_str("\nThe HTML version is too large to upload.");
+    }
+
+    let mut message = CreateMessage::new().content(content);
+    if with_html {
+        message = message.add_file(CreateAttachment::bytes(
+            transcript.html.as_bytes(),
+            format!("transcript-{}.html", channel.name),
+        ));
+    }
+    let message = message.add_file(CreateAttachment::bytes(
+        text.as_bytes(),
+        format!("transcript-{}.txt", channel.name),
+    ));
+
+    log_channel.send_message(http, message).await?;
+
+    Ok(())
+}
+
+/// The longest prefix of `text` that is at most `max` bytes and ends on a
+/// character boundary.
+fn truncate_bytes(text: &str, max: usize) -> &str {
+    if text.len() <= max {
+        return text;
+    }
+    let mut end = max;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
@@ -1,21 +1,38 @@
 mod bot;
 mod commands;
 mod config;
+mod error;
 mod logging;
+mod permissions;
+mod response;
+mod router;
+mod scheduler;
+mod setup;
+mod telemetry;
+mod tickets;
+mod transcript;
 
-use bot::run;
+use bot::{run, sync_commands};
 use config::Config;
+use std::env;
 use std::sync::Arc;
 
 #[tokio::main]
 async fn main() {
+    let _log_guard = telemetry::init();
+
     if let Err(e) = run_bot().await {
-        eprintln!("Error: {}", e);
+        tracing::error!(error = %e, "Bot stopped");
     }
 }
 
 async fn run_bot() -> Result<(), Box<dyn std::error::Error>> {
     let config = Arc::new(Config::new().await?);
+    if env::args().any(|arg| arg == "--sync-commands") {
+        let clear_global = env::args().any(|arg| arg == "--clear-global-commands");
+        sync_commands(config, clear_global).await?;
+        return Ok(());
+    }
     run(config).await?;
     Ok(())
 }
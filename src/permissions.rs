@@ -0,0 +1,187 @@
+use crate::commands::InteractionContext;
+use crate::config::Config;
+use crate::error::TicketError;
+use serenity::all::{CacheHttp, GuildChannel, GuildId, Mentionable, Permissions};
+use serenity::prelude::SerenityError;
+use std::sync::Arc;
+
+/// Discord permissions that mark a member as a bot admin. Also used as the
+/// `default_member_permissions` of admin commands so they stay hidden from
+/// everybody else.
+pub const ADMIN_PERMISSIONS: Permissions = Permissions::MANAGE_GUILD;
+
+/// What the bot needs in the category tickets are created in: creating the
+/// ticket channels, editing their permission overwrites, posting the welcome
+/// message and reading the history back for transcripts.
+pub const CATEGORY_PERMISSIONS: Permissions = Permissions::VIEW_CHANNEL
+    .union(Permissions::MANAGE_CHANNELS)
+    .union(Permissions::MANAGE_ROLES)
+    .union(Permissions::SEND_MESSAGES)
+    .union(Permissions::EMBED_LINKS)
+    .union(Permissions::READ_MESSAGE_HISTORY);
+
+/// What the bot needs in the log channel: posting log embeds and transcripts.
+pub const LOG_CHANNEL_PERMISSIONS: Permissions = Permissions::VIEW_CHANNEL
+    .union(Permissions::SEND_MESSAGES)
+    .union(Permissions::EMBED_LINKS)
+    .union(Permissions::ATTACH_FILES);
+
+/// What the bot needs in the channel the ticket panel is posted in.
+pub const PANEL_PERMISSIONS: Permissions = Permissions::VIEW_CHANNEL
+    .union(Permissions::SEND_MESSAGES)
+    .union(Permissions::EMBED_LINKS);
+
+/// Who may run a command, from least to most privileged. A member with a
+/// higher level can always run commands that require a lower one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PermissionLevel {
+    Everyone,
+    Participant,
+    Opener,
+    Staff,
+    Admin,
+}
+
+/// Works out the highest level the invoking member holds in the channel the
+/// interaction came from.
+pub async fn member_level(
+    interaction: &impl InteractionContext,
+    config: &Arc<Config>,
+) -> Result<PermissionLevel, sqlx::Error> {
+    let Some(member) = interaction.member() else {
+        return Ok(PermissionLevel::Everyone);
+    };
+    let permissions = member.permissions.unwrap_or_else(Permissions::empty);
+
+    if permissions.administrator() || permissions.contains(ADMIN_PERMISSIONS) {
+        return Ok(PermissionLevel::Admin);
+    }
+    if permissions.manage_messages() {
+        return Ok(PermissionLevel::Staff);
+    }
+
+    let support_roles = config.get_support_roles(member.guild_id).await?;
+    if member.roles.iter().any(|role| support_roles.contains(role)) {
+        return Ok(PermissionLevel::Staff);
+    }
+
+    let Some(ticket) = config
+        .tickets
+        .find_by_channel(interaction.channel_id())
+        .await?
+    else {
+        return Ok(PermissionLevel::Everyone);
+    };
+
+    if let Some(type_id) = ticket.type_id {
+        let type_roles = config.get_ticket_type_roles(type_id).await?;
+        if member.roles.iter().any(|role| type_roles.contains(role)) {
+            return Ok(PermissionLevel::Staff);
+        }
+    }
+    if let Some(tier) = config
+        .get_escalation_tier(member.guild_id, ticket.escalation_level)
+        .await?
+    {
+        if member.roles.contains(&tier.role_id()) {
+            return Ok(PermissionLevel::Staff);
+        }
+    }
+
+    if ticket.opener_id() == member.user.id {
+        Ok(PermissionLevel::Opener)
+    } else if config
+        .tickets
+        .is_participant(&ticket, member.user.id)
+        .await?
+    {
+        Ok(PermissionLevel::Participant)
+    } else {
+        Ok(PermissionLevel::Everyone)
+    }
+}
+
+pub async fn authorize(
+    interaction: &impl InteractionContext,
+    required: PermissionLevel,
+    config: &Arc<Config>,
+) -> Result<bool, sqlx::Error> {
+    if required == PermissionLevel::Everyone {
+        return Ok(true);
+    }
+    Ok(member_level(interaction, config).await? >= required)
+}
+
+/// The permissions out of `required` the bot does not have in `channel`.
+pub async fn missing_bot_permissions(
+    http: &impl CacheHttp,
+    channel: &GuildChannel,
+    required: Permissions,
+) -> Result<Permissions, SerenityError> {
+    let guild = channel.guild_id.to_partial_guild(http).await?;
+    let member = channel.guild_id.current_user_member(http.http()).await?;
+    Ok(required.difference(guild.user_permissions_in(channel, &member)))
+}
+
+/// The permissions out of `required` the bot does not have server-wide,
+/// ignoring channel overwrites.
+pub async fn missing_bot_guild_permissions(
+    http: &impl CacheHttp,
+    guild_id: GuildId,
+    required: Permissions,
+) -> Result<Permissions, SerenityError> {
+    let guild = guild_id.to_partial_guild(http).await?;
+    let member = guild_id.current_user_member(http.http()).await?;
+    if guild.owner_id == member.user.id {
+        return Ok(Permissions::empty());
+    }
+
+    let granted = member
+        .roles
+        .iter()
+        .chain([&guild_id.everyone_role()])
+        .filter_map(|role_id| guild.roles.get(role_id))
+        .fold(Permissions::empty(), |granted, role| {
+            granted | role.permissions
+        });
+    if granted.administrator() {
+        return Ok(Permissions::empty());
+    }
+    Ok(required.difference(granted))
+}
+
+/// Fails, naming what is missing, unless the bot has all of `required` in
+/// `channel`.
+pub async fn require_bot_permissions(
+    http: &impl CacheHttp,
+    channel: &GuildChannel,
+    required: Permissions,
+) -> Result<(), TicketError> {
+    let missing = missing_bot_permissions(http, channel, required).await?;
+    check_missing(missing, &channel.mention().to_string())
+}
+
+/// Fails, naming what is missing, unless the bot has all of `required` in
+/// `guild_id`.
+pub async fn require_bot_guild_permissions(
+    http: &impl CacheHttp,
+    guild_id: GuildId,
+    required: Permissions,
+) -> Result<(), TicketError> {
+    let missing = missing_bot_guild_permissions(http, guild_id, required).await?;
+    check_missing(missing, "this server")
+}
+
+fn check_missing(missing: Permissions, place: &str) -> Result<(), TicketError> {
+    if missing.is_empty() {
+        return Ok(());
+    }
+    Err(TicketError::Validation(
+        format!(
+            "I am missing these permissions in {}: {}.",
+            place,
+            missing.get_permission_names().join(", ")
+        )
+        .into(),
+    ))
+}
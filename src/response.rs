@@ -0,0 +1,118 @@
+use crate::error::TicketError;
+use serenity::{all::*, async_trait, prelude::SerenityError};
+use std::future::Future;
+use tracing::error;
+
+/// Interactions that can be acknowledged first and answered once the work is
+/// done. Discord only waits three seconds for the first response, which is
+/// not enough for creating channels or building transcripts.
+#[async_trait]
+pub trait DeferredResponse {
+    /// Acknowledges the interaction with a "thinking…" placeholder.
+    async fn defer_response(&self, http: &Http, ephemeral: bool) -> Result<(), SerenityError>;
+
+    /// Replaces the placeholder with `content`.
+    async fn edit_deferred(&self, http: &Http, content: &str) -> Result<(), SerenityError>;
+
+    /// Replaces the placeholder with `embed` when it is already ephemeral, or
+    /// removes it and answers with an ephemeral follow-up instead, so errors
+    /// only show up for the member who ran into them.
+    async fn show_ephemeral(
+        &self,
+        http: &Http,
+        embed: CreateEmbed,
+        deferred_ephemeral: bool,
+    ) -> Result<(), SerenityError>;
+}
+
+/// The three interaction types share the same response methods without a
+/// common trait for them, so the implementation is spelled out once here.
+macro_rules! impl_deferred_response {
+    ($($interaction:ty),+) => {$(
+        #[async_trait]
+        impl DeferredResponse for $interaction {
+            async fn defer_response(&self, http: &Http, ephemeral: bool) -> Result<(), SerenityError> {
+                self.create_response(
+                    http,
+                    CreateInteractionResponse::Defer(
+                        CreateInteractionResponseMessage::new().ephemeral(ephemeral),
+                    ),
+                )
+                .await
+            }
+
+            async fn edit_deferred(&self, http: &Http, content: &str) -> Result<(), SerenityError> {
+                self.edit_response(http, EditInteractionResponse::new().content(content))
+                    .await
+                    .map(|_| ())
+            }
+
+            async fn show_ephemeral(
+                &self,
+                http: &Http,
+                embed: CreateEmbed,
+                deferred_ephemeral: bool,
+            ) -> Result<(), SerenityError> {
+                if deferred_ephemeral {
+                    return self
+                        .edit_response(http, EditInteractionResponse::new().embed(embed))
+                        .await
+                        .map(|_| ());
+                }
+
+                self.delete_response(http).await?;
+                self.create_followup(
+                    http,
+                    CreateInteractionResponseFollowup::new()
+                        .embed(embed)
+                        .ephemeral(true),
+                )
+                .await
+                .map(|_| ())
+            }
+        }
+    )+};
+}
+
+impl_deferred_response!(CommandInteraction, ComponentInteraction, ModalInteraction);
+
+/// The embed members see when their interaction failed.
+pub fn error_embed(error: &TicketError) -> CreateEmbed {
+    CreateEmbed::new()
+        .title(match error {
+            TicketError::Permission(_) => "Not Allowed",
+            TicketError::Validation(_) | TicketError::NotATicket => "Cannot Do That",
+            TicketError::Discord(_) | TicketError::Database(_) => "Something Went Wrong",
+        })
+        .description(error.user_message())
+        .color(0xff0000)
+}
+
+/// Defers `interaction`, runs `handler` and puts its result in the deferred
+/// response. Errors are shown as an embed to the invoking member only, even
+/// when the response would otherwise have been public.
+pub async fn respond_deferred<I, F>(ctx: &Context, interaction: &I, ephemeral: bool, handler: F)
+where
+    I: DeferredResponse + Sync,
+    F: Future<Output = Result<String, TicketError>>,
+{
+    if let Err(why) = interaction.defer_response(&ctx.http, ephemeral).await {
+        error!(error = %why, "Cannot defer interaction response");
+        return;
+    }
+
+    let result = match handler.await {
+        Ok(content) => interaction.edit_deferred(&ctx.http, &content).await,
+        Err(e) => {
+            if e.is_internal() {
+                error!(error = %e, "Error handling interaction");
+            }
+            interaction
+                .show_ephemeral(&ctx.http, error_embed(&e), ephemeral)
+                .await
+        }
+    };
+    if let Err(why) = result {
+        error!(error = %why, "Cannot respond to interaction");
+    }
+}
@@ -0,0 +1,1562 @@
+use crate::commands::*;
+use crate::config::Config;
+use crate::error::TicketError;
+use crate::permissions::{PermissionLevel, ADMIN_PERMISSIONS};
+use crate::response::respond_deferred;
+use crate::setup::{run_setup_step, ticket_setup};
+use serde_json::Value;
+use serenity::{all::*, prelude::SerenityError};
+use std::fmt;
+use std::future::{ready, Future};
+use std::pin::Pin;
+use std::sync::Arc;
+use tracing::error;
+
+pub type HandlerFuture<'a> = Pin<Box<dyn Future<Output = Result<String, TicketError>> + Send + 'a>>;
+
+/// A slash command with everything needed to register and run it, so the two
+/// cannot drift apart.
+pub struct SlashCommand {
+    pub name: &'static str,
+    /// Who may run the command. Admin commands are also hidden from everybody
+    /// else in the command picker.
+    pub permission: PermissionLevel,
+    /// Subcommands, or subcommand groups, that need a different level than
+    /// `permission`.
+    pub subcommand_permissions: &'static [(&'static str, PermissionLevel)],
+    /// The command that replaces this one, for old names kept as deprecated
+    /// aliases. Remove those after the next release.
+    pub replaced_by: Option<&'static str>,
+    /// Whether only the member running the command sees the response, for
+    /// settings and lookups that should not be posted in whatever channel
+    /// they are run in.
+    pub ephemeral: bool,
+    /// Subcommands answered privately even though `ephemeral` is false.
+    pub ephemeral_subcommands: &'static [&'static str],
+    /// Adds the description and options to the command named `name`.
+    pub register: fn(CreateCommand) -> CreateCommand,
+    pub run: for<'a> fn(&'a Context, &'a CommandInteraction, &'a Arc<Config>) -> HandlerFuture<'a>,
+}
+
+impl SlashCommand {
+    pub fn create(&self) -> CreateCommand {
+        let mut command = (self.register)(CreateCommand::new(self.name)).dm_permission(false);
+        if let Some(replacement) = self.replaced_by {
+            command = command.description(format!("Deprecated, use {} instead", replacement));
+        }
+        if self.permission == PermissionLevel::Admin {
+            command.default_member_permissions(ADMIN_PERMISSIONS)
+        } else {
+            command
+        }
+    }
+
+    /// The level needed to run the subcommand `command` invoked.
+    pub fn required_permission(&self, command: &CommandInteraction) -> PermissionLevel {
+        let Some(invoked) = command.data.options.first() else {
+            return self.permission;
+        };
+        self.subcommand_permissions
+            .iter()
+            .find(|(name, _)| *name == invoked.name)
+            .map_or(self.permission, |(_, permission)| *permission)
+    }
+
+    /// Whether the response to the subcommand `command` invoked is private.
+    pub fn is_ephemeral(&self, command: &CommandInteraction) -> bool {
+        self.ephemeral
+            || command
+                .data
+                .options
+                .first()
+                .is_some_and(|invoked| self.ephemeral_subcommands.contains(&invoked.name.as_str()))
+    }
+
+    /// Runs the command, pointing members at the replacement of a deprecated
+    /// alias.
+    pub async fn execute(
+        &self,
+        ctx: &Context,
+        command: &CommandInteraction,
+        config: &Arc<Config>,
+    ) -> Result<String, TicketError> {
+        let content = (self.run)(ctx, command, config).await?;
+        Ok(match self.replaced_by {
+            Some(replacement) => format!(
+                "{}\n-# `/{}` is deprecated and will be removed, use `{}` instead.",
+                content, self.name, replacement
+            ),
+            None => content,
+        })
+    }
+}
+
+pub fn find_command(name: &str) -> Option<&'static SlashCommand> {
+    COMMANDS.iter().find(|command| command.name == name)
+}
+
+/// Where slash commands are registered. Guild commands show up right away,
+/// which makes them handy while developing; global ones can take a while.
+#[derive(Debug, Clone, Copy)]
+pub enum CommandScope {
+    Global,
+    Guild(GuildId),
+}
+
+/// What [`sync_commands`] had to change.
+#[derive(Debug, Default)]
+pub struct SyncSummary {
+    pub created: usize,
+    pub updated: usize,
+    pub deleted: usize,
+    pub unchanged: usize,
+}
+
+/// Brings the commands registered in `scope` in line with [`COMMANDS`]:
+/// commands that are missing or differ are (re)created, ones that are no
+/// longer in the registry are deleted, and the rest are left alone.
+pub async fn sync_commands(http: &Http, scope: CommandScope) -> Result<SyncSummary, SerenityError> {
+    let existing = match scope {
+        CommandScope::Global => Command::get_global_commands(http).await?,
+        CommandScope::Guild(guild_id) => guild_id.get_commands(http).await?,
+    };
+    let mut summary = SyncSummary::default();
+
+    for slash in COMMANDS {
+        let desired = slash.create();
+        let current = existing.iter().find(|command| command.name == slash.name);
+        if current.is_some_and(|current| same_command(current, &desired)) {
+            summary.unchanged += 1;
+            continue;
+        }
+
+        match scope {
+            CommandScope::Global => Command::create_global_command(http, desired).await?,
+            CommandScope::Guild(guild_id) => guild_id.create_command(http, desired).await?,
+        };
+        if current.is_some() {
+            summary.updated += 1;
+        } else {
+            summary.created += 1;
+        }
+    }
+
+    for command in existing
+        .iter()
+        .filter(|command| find_command(&command.name).is_none())
+    {
+        match scope {
+            CommandScope::Global => Command::delete_global_command(http, command.id).await?,
+            CommandScope::Guild(guild_id) => guild_id.delete_command(http, command.id).await?,
+        }
+        summary.deleted += 1;
+    }
+
+    Ok(summary)
+}
+
+/// Deletes every global command of the application, in every guild, so
+/// commands synced to a single guild do not show up twice there. Returns how
+/// many were deleted.
+pub async fn clear_global_commands(http: &Http) -> Result<usize, SerenityError> {
+    let commands = Command::get_global_commands(http).await?;
+    for command in &commands {
+        Command::delete_global_command(http, command.id).await?;
+    }
+    Ok(commands.len())
+}
+
+/// Whether the registered `current` command already matches `desired`. Both
+/// are compared as the JSON Discord deals in, leaving out the fields Discord
+/// assigns and the ones that are unset or at their default.
+fn same_command(current: &Command, desired: &CreateCommand) -> bool {
+    const FIELDS: [&str; 6] = [
+        "name",
+        "description",
+        "options",
+        "default_member_permissions",
+        "dm_permission",
+        "nsfw",
+    ];
+    let (Ok(Value::Object(current)), Ok(Value::Object(desired))) =
+        (serde_json::to_value(current), serde_json::to_value(desired))
+    else {
+        return false;
+    };
+
+    FIELDS.iter().all(|field| {
+        let current = current.get(*field).cloned().map(without_defaults);
+        let desired = desired.get(*field).cloned().map(without_defaults);
+        current.flatten() == desired.flatten()
+    })
+}
+
+/// Drops nulls, `false`, empty arrays and empty objects from `value`, or
+/// returns `None` if `value` is one of them itself.
+fn without_defaults(value: Value) -> Option<Value> {
+    match value {
+        Value::Null | Value::Bool(false) => None,
+        Value::Array(items) if items.is_empty() => None,
+        Value::Array(items) => Some(Value::Array(
+            items.into_iter().filter_map(without_defaults).collect(),
+        )),
+        Value::Object(fields) => {
+            let fields = fields
+                .into_iter()
+                .filter_map(|(key, value)| Some((key, without_defaults(value)?)))
+                .collect::<serde_json::Map<_, _>>();
+            (!fields.is_empty()).then_some(Value::Object(fields))
+        }
+        value => Some(value),
+    }
+}
+
+pub static COMMANDS: &[SlashCommand] = &[
+    SlashCommand {
+        name: "init",
+        permission: PermissionLevel::Admin,
+        subcommand_permissions: &[],
+        replaced_by: Some("/ticketconfig panel"),
+        ephemeral: true,
+        ephemeral_subcommands: &[],
+        register: |command| command.description("Initialize the ticket embed"),
+        run: |ctx, command, config| Box::pin(init(ctx, command, config)),
+    },
+    SlashCommand {
+        name: "close",
+        permission: PermissionLevel::Participant,
+        subcommand_permissions: &[],
+        replaced_by: Some("/ticket close"),
+        ephemeral: false,
+        ephemeral_subcommands: &[],
+        register: |command| {
+            command
+                .description("Close the current ticket")
+                .add_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "reason",
+                        "Why the ticket is being closed",
+                    )
+                    .max_length(1000),
+                )
+                .add_option(CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "delay",
+                    "How long to wait before closing, e.g. 30s, 10m or 2h",
+                ))
+        },
+        run: |ctx, command, config| Box::pin(close_command(ctx, command, config)),
+    },
+    SlashCommand {
+        name: "reopen",
+        permission: PermissionLevel::Staff,
+        subcommand_permissions: &[],
+        replaced_by: Some("/ticket reopen"),
+        ephemeral: false,
+        ephemeral_subcommands: &[],
+        register: |command| command.description("Reopen this archived ticket"),
+        run: |ctx, command, config| Box::pin(reopen(ctx, command, config)),
+    },
+    SlashCommand {
+        name: "claim",
+        permission: PermissionLevel::Staff,
+        subcommand_permissions: &[],
+        replaced_by: Some("/ticket claim"),
+        ephemeral: false,
+        ephemeral_subcommands: &[],
+        register: |command| command.description("Claim this ticket so other staff leave it to you"),
+        run: |ctx, command, config| Box::pin(claim(ctx, command, config)),
+    },
+    SlashCommand {
+        name: "unclaim",
+        permission: PermissionLevel::Staff,
+        subcommand_permissions: &[],
+        replaced_by: Some("/ticket unclaim"),
+        ephemeral: false,
+        ephemeral_subcommands: &[],
+        register: |command| command.description("Release your claim on this ticket"),
+        run: |ctx, command, config| Box::pin(unclaim(ctx, command, config)),
+    },
+    SlashCommand {
+        name: "transfer",
+        permission: PermissionLevel::Staff,
+        subcommand_permissions: &[],
+        replaced_by: Some("/ticket transfer"),
+        ephemeral: false,
+        ephemeral_subcommands: &[],
+        register: |command| {
+            command
+                .description("Make another staff member responsible for this ticket")
+                .set_options(transfer_options())
+        },
+        run: |ctx, command, config| Box::pin(transfer(ctx, command, config)),
+    },
+    SlashCommand {
+        name: "escalate",
+        permission: PermissionLevel::Staff,
+        subcommand_permissions: &[],
+        replaced_by: Some("/ticket escalate"),
+        ephemeral: false,
+        ephemeral_subcommands: &[],
+        register: |command| {
+            command
+                .description("Escalate this ticket to the next staff tier")
+                .set_options(escalate_options())
+        },
+        run: |ctx, command, config| {
+            Box::pin(escalate(
+                ctx,
+                command,
+                command_option_str(command, "reason"),
+                config,
+            ))
+        },
+    },
+    SlashCommand {
+        name: "tickets",
+        permission: PermissionLevel::Staff,
+        subcommand_permissions: &[],
+        replaced_by: Some("/ticket list"),
+        ephemeral: true,
+        ephemeral_subcommands: &[],
+        register: |command| {
+            command
+                .description("List the open tickets in this server")
+                .set_options(list_tickets_options())
+        },
+        run: |_, command, config| Box::pin(list_tickets(command, config)),
+    },
+    SlashCommand {
+        name: "ticket",
+        permission: PermissionLevel::Everyone,
+        subcommand_permissions: &[
+            ("close", PermissionLevel::Participant),
+            ("add", PermissionLevel::Opener),
+            ("remove", PermissionLevel::Opener),
+            ("rename", PermissionLevel::Staff),
+            ("claim", PermissionLevel::Staff),
+            ("history", PermissionLevel::Staff),
+            ("log", PermissionLevel::Staff),
+            ("reopen", PermissionLevel::Staff),
+            ("unclaim", PermissionLevel::Staff),
+            ("transfer", PermissionLevel::Staff),
+            ("escalate", PermissionLevel::Staff),
+            ("list", PermissionLevel::Staff),
+        ],
+        replaced_by: None,
+        ephemeral: false,
+        ephemeral_subcommands: &["history", "log", "list"],
+        register: |command| {
+            command
+                .description("Open, manage and look through tickets")
+                .add_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::SubCommand,
+                        "open",
+                        "Open a new ticket",
+                    )
+                    .add_sub_option(CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "type",
+                        "The ticket type name",
+                    )),
+                )
+                .add_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::SubCommand,
+                        "close",
+                        "Close this ticket",
+                    )
+                    .add_sub_option(
+                        CreateCommandOption::new(
+                            CommandOptionType::String,
+                            "reason",
+                            "Why the ticket is being closed",
+                        )
+                        .max_length(1000),
+                    )
+                    .add_sub_option(CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "delay",
+                        "How long to wait before closing, e.g. 30s, 10m or 2h",
+                    )),
+                )
+                .add_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::SubCommand,
+                        "add",
+                        "Add a user to this ticket",
+                    )
+                    .add_sub_option(
+                        CreateCommandOption::new(
+                            CommandOptionType::User,
+                            "user",
+                            "The user to add",
+                        )
+                        .required(true),
+                    ),
+                )
+                .add_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::SubCommand,
+                        "remove",
+                        "Remove a user from this ticket",
+                    )
+                    .add_sub_option(
+                        CreateCommandOption::new(
+                            CommandOptionType::User,
+                            "user",
+                            "The user to remove",
+                        )
+                        .required(true),
+                    ),
+                )
+                .add_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::SubCommand,
+                        "rename",
+                        "Rename this ticket's channel",
+                    )
+                    .add_sub_option(
+                        CreateCommandOption::new(CommandOptionType::String, "name", "New name")
+                            .required(true)
+                            .max_length(100),
+                    ),
+                )
+                .add_option(CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "claim",
+                    "Claim this ticket so other staff leave it to you",
+                ))
+                .add_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::SubCommand,
+                        "history",
+                        "Show everything that happened in this ticket",
+                    )
+                    .add_sub_option(page_option()),
+                )
+                .add_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::SubCommand,
+                        "log",
+                        "Show every ticket event involving a member",
+                    )
+                    .add_sub_option(
+                        CreateCommandOption::new(CommandOptionType::User, "user", "The member")
+                            .required(true),
+                    )
+                    .add_sub_option(page_option()),
+                )
+                .add_option(CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "reopen",
+                    "Reopen this archived ticket",
+                ))
+                .add_option(CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "unclaim",
+                    "Release your claim on this ticket",
+                ))
+                .add_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::SubCommand,
+                        "transfer",
+                        "Make another staff member responsible for this ticket",
+                    )
+                    .set_sub_options(transfer_options()),
+                )
+                .add_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::SubCommand,
+                        "escalate",
+                        "Escalate this ticket to the next staff tier",
+                    )
+                    .set_sub_options(escalate_options()),
+                )
+                .add_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::SubCommand,
+                        "list",
+                        "List the open tickets in this server",
+                    )
+                    .set_sub_options(list_tickets_options()),
+                )
+        },
+        run: |ctx, command, config| Box::pin(ticket(ctx, command, config)),
+    },
+    SlashCommand {
+        name: "ticketconfig",
+        permission: PermissionLevel::Admin,
+        subcommand_permissions: &[],
+        replaced_by: None,
+        ephemeral: true,
+        ephemeral_subcommands: &[],
+        register: |command| {
+            command
+                .description("Set up the ticket system")
+                .add_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::SubCommand,
+                        "category",
+                        "Set the category new tickets go into",
+                    )
+                    .add_sub_option(
+                        CreateCommandOption::new(
+                            CommandOptionType::Channel,
+                            "category",
+                            "Category new tickets are created in",
+                        )
+                        .channel_types(vec![ChannelType::Category])
+                        .required(true),
+                    ),
+                )
+                .add_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::SubCommand,
+                        "logchannel",
+                        "Set the channel ticket actions are logged in",
+                    )
+                    .add_sub_option(
+                        CreateCommandOption::new(
+                            CommandOptionType::Channel,
+                            "channel",
+                            "Channel ticket actions are logged in",
+                        )
+                        .channel_types(vec![ChannelType::Text, ChannelType::News])
+                        .required(true),
+                    ),
+                )
+                .add_option(CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "panel",
+                    "Post the ticket panel in this channel",
+                ))
+                .add_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::SubCommandGroup,
+                        "roles",
+                        "Manage the roles that can see every ticket",
+                    )
+                    .set_sub_options(support_role_subcommands()),
+                )
+                .add_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::SubCommandGroup,
+                        "types",
+                        "Manage the kinds of tickets members can open",
+                    )
+                    .set_sub_options(ticket_type_subcommands()),
+                )
+                .add_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::SubCommand,
+                        "limits",
+                        "Limit how many tickets members can open",
+                    )
+                    .set_sub_options(ticket_limits_options()),
+                )
+                .add_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::SubCommand,
+                        "template",
+                        "Set how new ticket channels are named",
+                    )
+                    .set_sub_options(ticket_template_options()),
+                )
+                .add_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::SubCommand,
+                        "archive",
+                        "Choose whether closed tickets are deleted or archived",
+                    )
+                    .set_sub_options(ticket_archive_options()),
+                )
+                .add_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::SubCommand,
+                        "claims",
+                        "Configure what claiming a ticket does",
+                    )
+                    .set_sub_options(ticket_claims_options()),
+                )
+                .add_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::SubCommandGroup,
+                        "escalation",
+                        "Manage the tiers tickets can be escalated to",
+                    )
+                    .set_sub_options(escalation_tier_subcommands()),
+                )
+                .add_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::SubCommand,
+                        "idle",
+                        "Close tickets automatically after a period of inactivity",
+                    )
+                    .set_sub_options(ticket_idle_options()),
+                )
+                .add_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::SubCommand,
+                        "closedelay",
+                        "Set how long closing a ticket waits for a cancel",
+                    )
+                    .set_sub_options(ticket_close_delay_options()),
+                )
+                .add_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::SubCommandGroup,
+                        "questions",
+                        "Manage the questions asked before a ticket is opened",
+                    )
+                    .set_sub_options(ticket_question_subcommands()),
+                )
+                .add_option(CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "show",
+                    "Show the current ticket configuration",
+                ))
+        },
+        run: |ctx, command, config| Box::pin(ticket_config(ctx, command, config)),
+    },
+    SlashCommand {
+        name: "ticketsetup",
+        permission: PermissionLevel::Admin,
+        subcommand_permissions: &[],
+        replaced_by: None,
+        ephemeral: true,
+        ephemeral_subcommands: &[],
+        register: |command| {
+            command.description("Set up tickets step by step and post the ticket panel")
+        },
+        run: |ctx, command, config| Box::pin(ticket_setup(ctx, command, config)),
+    },
+    SlashCommand {
+        name: "adduser",
+        permission: PermissionLevel::Opener,
+        subcommand_permissions: &[],
+        replaced_by: Some("/ticket add"),
+        ephemeral: false,
+        ephemeral_subcommands: &[],
+        register: |command| {
+            command.description("Add a user to the ticket").add_option(
+                CreateCommandOption::new(CommandOptionType::User, "user", "The user to add")
+                    .required(true),
+            )
+        },
+        run: |ctx, command, config| Box::pin(add_user(ctx, command, config)),
+    },
+    SlashCommand {
+        name: "removeuser",
+        permission: PermissionLevel::Opener,
+        subcommand_permissions: &[],
+        replaced_by: Some("/ticket remove"),
+        ephemeral: false,
+        ephemeral_subcommands: &[],
+        register: |command| {
+            command
+                .description("Remove a user from the ticket")
+                .add_option(
+                    CreateCommandOption::new(CommandOptionType::User, "user", "The user to remove")
+                        .required(true),
+                )
+        },
+        run: |ctx, command, config| Box::pin(remove_user(ctx, command, config)),
+    },
+    SlashCommand {
+        name: "setcategory",
+        permission: PermissionLevel::Admin,
+        subcommand_permissions: &[],
+        replaced_by: Some("/ticketconfig category"),
+        ephemeral: true,
+        ephemeral_subcommands: &[],
+        register: |command| {
+            command
+                .description("Set the category new tickets are created in")
+                .add_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::Channel,
+                        "category",
+                        "Category new tickets are created in",
+                    )
+                    .channel_types(vec![ChannelType::Category])
+                    .required(true),
+                )
+        },
+        run: |ctx, command, config| Box::pin(set_category(ctx, command, config)),
+    },
+    SlashCommand {
+        name: "setlogchannel",
+        permission: PermissionLevel::Admin,
+        subcommand_permissions: &[],
+        replaced_by: Some("/ticketconfig logchannel"),
+        ephemeral: true,
+        ephemeral_subcommands: &[],
+        register: |command| {
+            command
+                .description("Set the channel ticket actions are logged in")
+                .add_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::Channel,
+                        "channel",
+                        "Channel ticket actions are logged in",
+                    )
+                    .channel_types(vec![ChannelType::Text, ChannelType::News])
+                    .required(true),
+                )
+        },
+        run: |ctx, command, config| Box::pin(set_log_channel(ctx, command, config)),
+    },
+    SlashCommand {
+        name: "supportrole",
+        permission: PermissionLevel::Admin,
+        subcommand_permissions: &[],
+        replaced_by: Some("/ticketconfig roles"),
+        ephemeral: true,
+        ephemeral_subcommands: &[],
+        register: |command| {
+            command
+                .description("Manage the roles that can see every ticket")
+                .set_options(support_role_subcommands())
+        },
+        run: |_, command, config| Box::pin(support_role(command, config)),
+    },
+    SlashCommand {
+        name: "tickettype",
+        permission: PermissionLevel::Admin,
+        subcommand_permissions: &[],
+        replaced_by: Some("/ticketconfig types"),
+        ephemeral: true,
+        ephemeral_subcommands: &[],
+        register: |command| {
+            command
+                .description("Manage the kinds of tickets members can open")
+                .set_options(ticket_type_subcommands())
+        },
+        run: |ctx, command, config| Box::pin(ticket_type(ctx, command, config)),
+    },
+    SlashCommand {
+        name: "ticketlimits",
+        permission: PermissionLevel::Admin,
+        subcommand_permissions: &[],
+        replaced_by: Some("/ticketconfig limits"),
+        ephemeral: true,
+        ephemeral_subcommands: &[],
+        register: |command| {
+            command
+                .description("Limit how many tickets members can open")
+                .set_options(ticket_limits_options())
+        },
+        run: |_, command, config| Box::pin(ticket_limits(command, config)),
+    },
+    SlashCommand {
+        name: "tickettemplate",
+        permission: PermissionLevel::Admin,
+        subcommand_permissions: &[],
+        replaced_by: Some("/ticketconfig template"),
+        ephemeral: true,
+        ephemeral_subcommands: &[],
+        register: |command| {
+            command
+                .description("Set how new ticket channels are named")
+                .set_options(ticket_template_options())
+        },
+        run: |_, command, config| Box::pin(ticket_template(command, config)),
+    },
+    SlashCommand {
+        name: "ticketarchive",
+        permission: PermissionLevel::Admin,
+        subcommand_permissions: &[],
+        replaced_by: Some("/ticketconfig archive"),
+        ephemeral: true,
+        ephemeral_subcommands: &[],
+        register: |command| {
+            command
+                .description("Choose whether closed tickets are deleted or archived")
+                .set_options(ticket_archive_options())
+        },
+        run: |ctx, command, config| Box::pin(ticket_archive(ctx, command, config)),
+    },
+    SlashCommand {
+        name: "ticketclaims",
+        permission: PermissionLevel::Admin,
+        subcommand_permissions: &[],
+        replaced_by: Some("/ticketconfig claims"),
+        ephemeral: true,
+        ephemeral_subcommands: &[],
+        register: |command| {
+            command
+                .description("Configure what claiming a ticket does")
+                .set_options(ticket_claims_options())
+        },
+        run: |_, command, config| Box::pin(ticket_claims(command, config)),
+    },
+    SlashCommand {
+        name: "escalationtier",
+        permission: PermissionLevel::Admin,
+        subcommand_permissions: &[],
+        replaced_by: Some("/ticketconfig escalation"),
+        ephemeral: true,
+        ephemeral_subcommands: &[],
+        register: |command| {
+            command
+                .description("Manage the tiers tickets can be escalated to")
+                .set_options(escalation_tier_subcommands())
+        },
+        run: |ctx, command, config| Box::pin(escalation_tier(ctx, command, config)),
+    },
+    SlashCommand {
+        name: "ticketidle",
+        permission: PermissionLevel::Admin,
+        subcommand_permissions: &[],
+        replaced_by: Some("/ticketconfig idle"),
+        ephemeral: true,
+        ephemeral_subcommands: &[],
+        register: |command| {
+            command
+                .description("Close tickets automatically after a period of inactivity")
+                .set_options(ticket_idle_options())
+        },
+        run: |_, command, config| Box::pin(ticket_idle(command, config)),
+    },
+    SlashCommand {
+        name: "ticketclosedelay",
+        permission: PermissionLevel::Admin,
+        subcommand_permissions: &[],
+        replaced_by: Some("/ticketconfig closedelay"),
+        ephemeral: true,
+        ephemeral_subcommands: &[],
+        register: |command| {
+            command
+                .description("Set how long closing a ticket waits for a cancel")
+                .set_options(ticket_close_delay_options())
+        },
+        run: |_, command, config| Box::pin(ticket_close_delay(command, config)),
+    },
+    SlashCommand {
+        name: "ticketquestion",
+        permission: PermissionLevel::Admin,
+        subcommand_permissions: &[],
+        replaced_by: Some("/ticketconfig questions"),
+        ephemeral: true,
+        ephemeral_subcommands: &[],
+        register: |command| {
+            command
+                .description("Manage the questions asked before a ticket is opened")
+                .set_options(ticket_question_subcommands())
+        },
+        run: |_, command, config| Box::pin(ticket_question(command, config)),
+    },
+];
+
+/// Custom ids of the buttons, select menus and modals the bot sends, written
+/// as `<prefix>` or `<prefix>:<parameter>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CustomId {
+    /// Panel button or select menu, with the ticket type for per-type buttons.
+    OpenTicket {
+        type_id: Option<i64>,
+    },
+    /// Intake form modal of a ticket type.
+    TicketForm {
+        type_id: i64,
+    },
+    CloseTicket,
+    /// Modal asking for the close reason.
+    CloseReason,
+    /// Cancels a scheduled close. Messages posted before closes were stored as
+    /// jobs carry no job id.
+    CancelClose {
+        job_id: Option<i64>,
+    },
+    ClaimTicket,
+    ReopenTicket,
+    /// `/ticketsetup` menus picking the category, log channel, staff roles
+    /// and panel channel.
+    SetupCategory,
+    SetupLogChannel,
+    SetupRoles,
+    SetupPanelChannel,
+    /// `/ticketsetup` buttons creating the category or log channel.
+    SetupCreateCategory,
+    SetupCreateLogChannel,
+    /// Finishes `/ticketsetup` by posting the panel. Carries the panel channel
+    /// picked so far, as the wizard keeps no other state.
+    SetupPostPanel {
+        channel_id: i64,
+    },
+}
+
+impl CustomId {
+    pub fn parse(custom_id: &str) -> Option<Self> {
+        let (prefix, param) = match custom_id.split_once(':') {
+            Some((prefix, param)) => (prefix, Some(param)),
+            None => (custom_id, None),
+        };
+        let id = param.and_then(|param| param.parse::<i64>().ok());
+
+        match prefix {
+            "open_ticket" => Some(CustomId::OpenTicket { type_id: id }),
+            "ticket_form" => id.map(|type_id| CustomId::TicketForm { type_id }),
+            "close_ticket" => Some(CustomId::CloseTicket),
+            "close_reason" => Some(CustomId::CloseReason),
+            "cancel_close" => Some(CustomId::CancelClose { job_id: id }),
+            "claim_ticket" => Some(CustomId::ClaimTicket),
+            "reopen_ticket" => Some(CustomId::ReopenTicket),
+            "setup_category" => Some(CustomId::SetupCategory),
+            "setup_log_channel" => Some(CustomId::SetupLogChannel),
+            "setup_roles" => Some(CustomId::SetupRoles),
+            "setup_panel_channel" => Some(CustomId::SetupPanelChannel),
+            "setup_create_category" => Some(CustomId::SetupCreateCategory),
+            "setup_create_log_channel" => Some(CustomId::SetupCreateLogChannel),
+            "setup_post_panel" => id
+                .filter(|channel_id| *channel_id > 0)
+                .map(|channel_id| CustomId::SetupPostPanel { channel_id }),
+            _ => None,
+        }
+    }
+
+    pub fn permission(self) -> PermissionLevel {
+        match self {
+            CustomId::OpenTicket { .. } | CustomId::TicketForm { .. } => PermissionLevel::Everyone,
+            CustomId::CloseTicket | CustomId::CloseReason | CustomId::CancelClose { .. } => {
+                PermissionLevel::Participant
+            }
+            CustomId::ClaimTicket | CustomId::ReopenTicket => PermissionLevel::Staff,
+            CustomId::SetupCategory
+            | CustomId::SetupLogChannel
+            | CustomId::SetupRoles
+            | CustomId::SetupPanelChannel
+            | CustomId::SetupCreateCategory
+            | CustomId::SetupCreateLogChannel
+            | CustomId::SetupPostPanel { .. } => PermissionLevel::Admin,
+        }
+    }
+}
+
+impl fmt::Display for CustomId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CustomId::OpenTicket { type_id: None } => write!(f, "open_ticket"),
+            CustomId::OpenTicket {
+                type_id: Some(type_id),
+            } => write!(f, "open_ticket:{}", type_id),
+            CustomId::TicketForm { type_id } => write!(f, "ticket_form:{}", type_id),
+            CustomId::CloseTicket => write!(f, "close_ticket"),
+            CustomId::CloseReason => write!(f, "close_reason"),
+            CustomId::CancelClose { job_id: None } => write!(f, "cancel_close"),
+            CustomId::CancelClose {
+                job_id: Some(job_id),
+            } => write!(f, "cancel_close:{}", job_id),
+            CustomId::ClaimTicket => write!(f, "claim_ticket"),
+            CustomId::ReopenTicket => write!(f, "reopen_ticket"),
+            CustomId::SetupCategory => write!(f, "setup_category"),
+            CustomId::SetupLogChannel => write!(f, "setup_log_channel"),
+            CustomId::SetupRoles => write!(f, "setup_roles"),
+            CustomId::SetupPanelChannel => write!(f, "setup_panel_channel"),
+            CustomId::SetupCreateCategory => write!(f, "setup_create_category"),
+            CustomId::SetupCreateLogChannel => write!(f, "setup_create_log_channel"),
+            CustomId::SetupPostPanel { channel_id } => {
+                write!(f, "setup_post_panel:{}", channel_id)
+            }
+        }
+    }
+}
+
+/// Handles a button click or menu selection. Modals have to be the first
+/// response, so the buttons that open one can only defer when they end up not
+/// needing it.
+pub async fn run_component(
+    ctx: &Context,
+    component: &ComponentInteraction,
+    id: CustomId,
+    config: &Arc<Config>,
+) {
+    match id {
+        CustomId::OpenTicket { type_id } => match ticket_form(component, type_id, config).await {
+            Ok(Some(modal)) => show_modal(ctx, component, modal).await,
+            Ok(None) => {
+                respond_deferred(
+                    ctx,
+                    component,
+                    true,
+                    open_ticket(ctx, component, type_id, config),
+                )
+                .await
+            }
+            Err(e) => respond_deferred(ctx, component, true, ready(Err(e))).await,
+        },
+        CustomId::CloseTicket => match close_form(component, config).await {
+            Ok(modal) => show_modal(ctx, component, modal).await,
+            Err(e) => respond_deferred(ctx, component, true, ready(Err(e))).await,
+        },
+        CustomId::CancelClose { job_id } => {
+            respond_deferred(
+                ctx,
+                component,
+                true,
+                cancel_close(ctx, component, job_id, config),
+            )
+            .await
+        }
+        CustomId::ClaimTicket => {
+            respond_deferred(ctx, component, false, claim(ctx, component, config)).await
+        }
+        CustomId::ReopenTicket => {
+            respond_deferred(ctx, component, false, reopen_ticket(ctx, component, config)).await
+        }
+        CustomId::SetupCategory
+        | CustomId::SetupLogChannel
+        | CustomId::SetupRoles
+        | CustomId::SetupPanelChannel
+        | CustomId::SetupCreateCategory
+        | CustomId::SetupCreateLogChannel
+        | CustomId::SetupPostPanel { .. } => run_setup_step(ctx, component, id, config).await,
+        CustomId::TicketForm { .. } | CustomId::CloseReason => {}
+    }
+}
+
+pub async fn run_modal(
+    ctx: &Context,
+    modal: &ModalInteraction,
+    id: CustomId,
+    config: &Arc<Config>,
+) {
+    match id {
+        CustomId::TicketForm { type_id } => {
+            respond_deferred(
+                ctx,
+                modal,
+                true,
+                submit_ticket_form(ctx, modal, type_id, config),
+            )
+            .await
+        }
+        CustomId::CloseReason => {
+            let reason = close_form_reason(modal);
+            respond_deferred(ctx, modal, true, close(ctx, modal, reason, None, config)).await
+        }
+        _ => {}
+    }
+}
+
+async fn show_modal(ctx: &Context, component: &ComponentInteraction, modal: CreateModal) {
+    if let Err(why) = component
+        .create_response(&ctx.http, CreateInteractionResponse::Modal(modal))
+        .await
+    {
+        error!(error = %why, "Error showing modal");
+    }
+}
+
+async fn close_command(
+    ctx: &Context,
+    command: &CommandInteraction,
+    config: &Arc<Config>,
+) -> Result<String, TicketError> {
+    let delay = command_option_str(command, "delay")
+        .map(parse_duration)
+        .transpose()?;
+    close(
+        ctx,
+        command,
+        command_option_str(command, "reason"),
+        delay,
+        config,
+    )
+    .await
+}
+
+fn ticket_type_name() -> CreateCommandOption {
+    CreateCommandOption::new(CommandOptionType::String, "name", "The ticket type name")
+        .required(true)
+}
+
+fn close_mode_option(name: &str, description: &str) -> CreateCommandOption {
+    CreateCommandOption::new(CommandOptionType::String, name, description)
+        .add_string_choice("Delete the channel", "delete")
+        .add_string_choice("Archive the channel", "archive")
+}
+
+fn page_option() -> CreateCommandOption {
+    CreateCommandOption::new(
+        CommandOptionType::Integer,
+        "page",
+        "Page to show, 1 by default",
+    )
+    .min_int_value(1)
+}
+
+fn escalation_level() -> CreateCommandOption {
+    CreateCommandOption::new(
+        CommandOptionType::Integer,
+        "level",
+        "Tier level, tickets escalate from lower to higher levels",
+    )
+    .required(true)
+    .min_int_value(1)
+}
+
+fn ticket_question_type() -> CreateCommandOption {
+    CreateCommandOption::new(CommandOptionType::String, "type", "The ticket type name")
+        .required(true)
+}
+
+fn ticket_type_fields(option: CreateCommandOption) -> CreateCommandOption {
+    option
+        .add_sub_option(
+            CreateCommandOption::new(
+                CommandOptionType::Channel,
+                "category",
+                "Category new tickets of this type go into",
+            )
+            .channel_types(vec![ChannelType::Category]),
+        )
+        .add_sub_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "welcome",
+            "Text of the first message in the ticket",
+        ))
+        .add_sub_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "template",
+            "Channel name, placeholders: {number} {user} {userid} {type}",
+        ))
+        .add_sub_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "emoji",
+            "Emoji shown on the panel",
+        ))
+        .add_sub_option(CreateCommandOption::new(
+            CommandOptionType::Boolean,
+            "require_reason",
+            "Whether closing these tickets requires a reason",
+        ))
+        .add_sub_option(close_mode_option(
+            "close_mode",
+            "What happens to these tickets when they are closed",
+        ))
+        .add_sub_option(
+            CreateCommandOption::new(
+                CommandOptionType::Integer,
+                "limit",
+                "Open tickets of this type a member may have instead of the server limit, 0 to unset",
+            )
+            .min_int_value(0),
+        )
+}
+
+// Options shared by the deprecated flat commands and the `/ticket` and
+// `/ticketconfig` subcommands replacing them.
+
+fn transfer_options() -> Vec<CreateCommandOption> {
+    vec![CreateCommandOption::new(
+        CommandOptionType::User,
+        "member",
+        "The staff member to hand the ticket to",
+    )
+    .required(true)]
+}
+
+fn escalate_options() -> Vec<CreateCommandOption> {
+    vec![CreateCommandOption::new(
+        CommandOptionType::String,
+        "reason",
+        "Why the ticket needs escalating",
+    )
+    .max_length(1000)]
+}
+
+fn list_tickets_options() -> Vec<CreateCommandOption> {
+    vec![
+        CreateCommandOption::new(
+            CommandOptionType::String,
+            "search",
+            "Search intake form answers of all tickets instead",
+        )
+        .max_length(100),
+        page_option(),
+    ]
+}
+
+fn support_role_subcommands() -> Vec<CreateCommandOption> {
+    vec![
+        CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "add",
+            "Give a role access to new tickets",
+        )
+        .add_sub_option(
+            CreateCommandOption::new(CommandOptionType::Role, "role", "The role").required(true),
+        ),
+        CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "remove",
+            "Stop giving a role access to new tickets",
+        )
+        .add_sub_option(
+            CreateCommandOption::new(CommandOptionType::Role, "role", "The role").required(true),
+        ),
+        CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "list",
+            "List the support roles",
+        ),
+    ]
+}
+
+fn ticket_type_subcommands() -> Vec<CreateCommandOption> {
+    vec![
+        ticket_type_fields(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "create",
+                "Create a ticket type",
+            )
+            .add_sub_option(ticket_type_name()),
+        ),
+        ticket_type_fields(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "edit",
+                "Change a ticket type",
+            )
+            .add_sub_option(ticket_type_name()),
+        ),
+        CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "delete",
+            "Delete a ticket type",
+        )
+        .add_sub_option(ticket_type_name()),
+        CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "list",
+            "List the ticket types",
+        ),
+        CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "addrole",
+            "Let a role staff tickets of this type",
+        )
+        .add_sub_option(ticket_type_name())
+        .add_sub_option(
+            CreateCommandOption::new(CommandOptionType::Role, "role", "The role").required(true),
+        ),
+        CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "removerole",
+            "Stop a role from staffing tickets of this type",
+        )
+        .add_sub_option(ticket_type_name())
+        .add_sub_option(
+            CreateCommandOption::new(CommandOptionType::Role, "role", "The role").required(true),
+        ),
+    ]
+}
+
+fn ticket_limits_options() -> Vec<CreateCommandOption> {
+    vec![
+        CreateCommandOption::new(
+            CommandOptionType::Integer,
+            "max_open",
+            "Open tickets a member may have at once, except types with their own limit",
+        )
+        .min_int_value(1),
+        CreateCommandOption::new(
+            CommandOptionType::Integer,
+            "cooldown",
+            "Seconds a member must wait between opening tickets",
+        )
+        .min_int_value(0),
+    ]
+}
+
+fn ticket_template_options() -> Vec<CreateCommandOption> {
+    vec![CreateCommandOption::new(
+        CommandOptionType::String,
+        "template",
+        "e.g. ticket-{number}, placeholders: {number} {user} {userid} {type}",
+    )
+    .max_length(100)]
+}
+
+fn ticket_archive_options() -> Vec<CreateCommandOption> {
+    vec![
+        close_mode_option(
+            "mode",
+            "What happens to ticket channels when they are closed",
+        ),
+        CreateCommandOption::new(
+            CommandOptionType::Channel,
+            "category",
+            "Category archived tickets are moved into",
+        )
+        .channel_types(vec![ChannelType::Category]),
+        CreateCommandOption::new(
+            CommandOptionType::Integer,
+            "retention",
+            "Hours archived tickets are kept before deletion, 0 to keep them",
+        )
+        .min_int_value(0),
+    ]
+}
+
+fn ticket_claims_options() -> Vec<CreateCommandOption> {
+    vec![CreateCommandOption::new(
+        CommandOptionType::Boolean,
+        "read_only",
+        "Whether other staff become read-only in claimed tickets",
+    )]
+}
+
+fn escalation_tier_subcommands() -> Vec<CreateCommandOption> {
+    vec![
+        CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "set",
+            "Create or change an escalation tier",
+        )
+        .add_sub_option(escalation_level())
+        .add_sub_option(
+            CreateCommandOption::new(CommandOptionType::String, "name", "Tier name")
+                .required(true)
+                .max_length(100),
+        )
+        .add_sub_option(
+            CreateCommandOption::new(
+                CommandOptionType::Role,
+                "role",
+                "Role that staffs tickets at this tier",
+            )
+            .required(true),
+        )
+        .add_sub_option(
+            CreateCommandOption::new(
+                CommandOptionType::Channel,
+                "category",
+                "Category escalated tickets are moved into",
+            )
+            .channel_types(vec![ChannelType::Category]),
+        ),
+        CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "remove",
+            "Remove an escalation tier",
+        )
+        .add_sub_option(escalation_level()),
+        CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "list",
+            "List the escalation tiers",
+        ),
+    ]
+}
+
+fn ticket_idle_options() -> Vec<CreateCommandOption> {
+    vec![
+        CreateCommandOption::new(
+            CommandOptionType::Integer,
+            "idle",
+            "Hours without messages before a warning is posted, 0 to turn off",
+        )
+        .min_int_value(0),
+        CreateCommandOption::new(
+            CommandOptionType::Integer,
+            "grace",
+            "Hours after the warning before the ticket is closed",
+        )
+        .min_int_value(1),
+    ]
+}
+
+fn ticket_close_delay_options() -> Vec<CreateCommandOption> {
+    vec![CreateCommandOption::new(
+        CommandOptionType::String,
+        "delay",
+        "e.g. 5s, 1m or 1h",
+    )]
+}
+
+fn ticket_question_subcommands() -> Vec<CreateCommandOption> {
+    vec![
+        CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "add",
+            "Ask a question before opening tickets of a type",
+        )
+        .add_sub_option(ticket_question_type())
+        .add_sub_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "question",
+                "The question, at most 45 characters",
+            )
+            .required(true)
+            .max_length(45),
+        )
+        .add_sub_option(
+            CreateCommandOption::new(CommandOptionType::String, "style", "Size of the answer box")
+                .add_string_choice("Short", "short")
+                .add_string_choice("Paragraph", "paragraph"),
+        )
+        .add_sub_option(CreateCommandOption::new(
+            CommandOptionType::Boolean,
+            "required",
+            "Whether an answer is required, defaults to true",
+        ))
+        .add_sub_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "placeholder",
+                "Hint shown in the empty answer box, at most 100 characters",
+            )
+            .max_length(100),
+        ),
+        CreateCommandOption::new(CommandOptionType::SubCommand, "remove", "Remove a question")
+            .add_sub_option(ticket_question_type())
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Integer,
+                    "position",
+                    "Number of the question, see list",
+                )
+                .required(true),
+            ),
+        CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "list",
+            "List the questions of a ticket type",
+        )
+        .add_sub_option(ticket_question_type()),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn custom_ids_round_trip() {
+        let ids = [
+            CustomId::OpenTicket { type_id: None },
+            CustomId::OpenTicket { type_id: Some(7) },
+            CustomId::TicketForm { type_id: 7 },
+            CustomId::CloseTicket,
+            CustomId::CloseReason,
+            CustomId::CancelClose { job_id: None },
+            CustomId::CancelClose { job_id: Some(42) },
+            CustomId::ClaimTicket,
+            CustomId::ReopenTicket,
+            CustomId::SetupCategory,
+            CustomId::SetupLogChannel,
+            CustomId::SetupRoles,
+            CustomId::SetupPanelChannel,
+            CustomId::SetupCreateCategory,
+            CustomId::SetupCreateLogChannel,
+            CustomId::SetupPostPanel {
+                channel_id: 1234567890,
+            },
+        ];
+        for id in ids {
+            assert_eq!(CustomId::parse(&id.to_string()), Some(id), "{}", id);
+        }
+    }
+
+    #[test]
+    fn custom_ids_reject_bad_input() {
+        assert_eq!(CustomId::parse("setup_post_panel:0"), None);
+        assert_eq!(CustomId::parse("setup_post_panel"), None);
+        assert_eq!(CustomId::parse("ticket_form"), None);
+        assert_eq!(CustomId::parse("ticket_form:abc"), None);
+        assert_eq!(CustomId::parse("something_else"), None);
+    }
+
+    /// What Discord sends back for `desired`: the same command with ids and
+    /// every unset option field filled in with its default.
+    fn registered(desired: &CreateCommand) -> Command {
+        let mut command = serde_json::to_value(desired).unwrap();
+        let fields = command.as_object_mut().unwrap();
+        fields.insert("id".into(), json!("1"));
+        fields.insert("application_id".into(), json!("2"));
+        fields.insert("version".into(), json!("3"));
+        fields.insert("type".into(), json!(1));
+        serde_json::from_value(command).unwrap()
+    }
+
+    #[test]
+    fn registered_commands_match_their_definitions() {
+        for slash in COMMANDS {
+            let desired = slash.create();
+            assert!(
+                same_command(&registered(&desired), &desired),
+                "/{} would be registered again on every start",
+                slash.name
+            );
+        }
+    }
+
+    #[test]
+    fn deprecated_aliases_point_at_existing_subcommands() {
+        for slash in COMMANDS {
+            let Some(replacement) = slash.replaced_by else {
+                continue;
+            };
+            let (name, subcommand) = replacement[1..].split_once(' ').unwrap();
+            let command = serde_json::to_value(find_command(name).unwrap().create()).unwrap();
+            assert!(
+                command["options"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .any(|option| option["name"] == subcommand),
+                "/{} points at missing {}",
+                slash.name,
+                replacement
+            );
+        }
+    }
+
+    #[test]
+    fn unset_options_match_options_set_to_defaults() {
+        let desired =
+            CreateCommand::new("ping")
+                .description("Ping")
+                .add_option(CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "text",
+                    "Some text",
+                ));
+        let current: Command = serde_json::from_value(json!({
+            "id": "1",
+            "application_id": "2",
+            "version": "3",
+            "type": 1,
+            "name": "ping",
+            "description": "Ping",
+            "default_member_permissions": null,
+            "dm_permission": null,
+            "nsfw": false,
+            "options": [{
+                "type": 3,
+                "name": "text",
+                "description": "Some text",
+                "required": false,
+                "choices": [],
+                "options": [],
+                "channel_types": [],
+                "autocomplete": false,
+            }],
+        }))
+        .unwrap();
+        assert!(same_command(&current, &desired));
+
+        let changed = CreateCommand::new("ping").description("Ping").add_option(
+            CreateCommandOption::new(CommandOptionType::String, "text", "Some text").required(true),
+        );
+        assert!(!same_command(&current, &changed));
+    }
+}
@@ -0,0 +1,224 @@
+use crate::commands::{close_channel, finish_close};
+use crate::config::Config;
+use crate::error::TicketError;
+use crate::tickets::{JobKind, ScheduledJob, TicketStatus};
+use serenity::all::*;
+use std::sync::Arc;
+use tokio::time::{interval, sleep, Duration};
+use tracing::{error, instrument, warn};
+
+/// How often due jobs are run and archived tickets are checked against their
+/// retention period.
+const TICK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Spawns the background task that runs scheduled ticket jobs and deletes
+/// archived ticket channels once their guild's retention period has passed.
+/// Jobs live in the database, so ones that came due while the bot was down
+/// run on the first tick after startup.
+pub fn start(http: Arc<Http>, config: Arc<Config>) {
+    tokio::spawn(async move {
+        match config.tickets.pending_jobs(JobKind::Close).await {
+            Ok(jobs) => {
+                for job in jobs {
+                    spawn_job(Arc::clone(&http), Arc::clone(&config), job);
+                }
+            }
+            Err(why) => error!(error = %why, "Error resuming pending closes"),
+        }
+
+        let mut ticker = interval(TICK_INTERVAL);
+        loop {
+            ticker.tick().await;
+            run_due_jobs(&http, &config).await;
+            purge_expired_archives(&http, &config).await;
+        }
+    });
+}
+
+/// Runs `job` right when it comes due instead of on the next tick. The job is
+/// still in the database, so if the bot restarts first it is resumed from
+/// there.
+pub fn spawn_job(http: Arc<Http>, config: Arc<Config>, job: ScheduledJob) {
+    tokio::spawn(async move {
+        let wait = job.run_at - Timestamp::now().unix_timestamp();
+        if wait > 0 {
+            sleep(Duration::from_secs(wait as u64)).await;
+        }
+        if let Err(why) = run_job(&http, &job, &config).await {
+            error!(error = %why, "Error running scheduled job");
+        }
+    });
+}
+
+/// Restarts the inactivity countdown of the ticket in `channel_id`, if there
+/// is an open one. Called for every message and when a ticket is opened or
+/// reopened.
+pub async fn record_activity(
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    config: &Config,
+) -> Result<(), sqlx::Error> {
+    let Some(ticket) = config
+        .tickets
+        .find_by_channel(channel_id)
+        .await?
+        .filter(|ticket| ticket.status == TicketStatus::Open)
+    else {
+        return Ok(());
+    };
+
+    let now = Timestamp::now().unix_timestamp();
+    config.tickets.record_activity(&ticket, now).await?;
+    config
+        .tickets
+        .cancel_job(&ticket, JobKind::IdleClose)
+        .await?;
+    match config.get_idle_close_hours(guild_id).await? {
+        Some(hours) => {
+            config
+                .tickets
+                .schedule_job(&ticket, JobKind::IdleWarning, now + hours * 3600)
+                .await
+        }
+        None => {
+            config
+                .tickets
+                .cancel_job(&ticket, JobKind::IdleWarning)
+                .await
+        }
+    }
+}
+
+async fn run_due_jobs(http: &Arc<Http>, config: &Arc<Config>) {
+    let now = Timestamp::now().unix_timestamp();
+    let jobs = match config.tickets.due_jobs(now).await {
+        Ok(jobs) => jobs,
+        Err(why) => {
+            error!(error = %why, "Error fetching scheduled jobs");
+            return;
+        }
+    };
+
+    for job in jobs {
+        if let Err(why) = run_job(http, &job, config).await {
+            error!(error = %why, "Error running scheduled job");
+        }
+    }
+}
+
+/// Runs `job` inside a span carrying its ticket and kind, so everything logged
+/// while it runs can be traced back to it. The job is only removed once it
+/// succeeded; until then it stays claimed for a later retry.
+#[instrument(skip_all, fields(ticket_id = job.ticket_id, kind = ?job.kind))]
+async fn run_job(
+    http: &Arc<Http>,
+    job: &ScheduledJob,
+    config: &Arc<Config>,
+) -> Result<(), TicketError> {
+    let now = Timestamp::now().unix_timestamp();
+    let Some(claimed_until) = config.tickets.claim_job(job, now).await? else {
+        return Ok(());
+    };
+
+    run_claimed_job(http, job, config).await?;
+    config.tickets.finish_job(job, claimed_until).await?;
+    Ok(())
+}
+
+async fn run_claimed_job(
+    http: &Arc<Http>,
+    job: &ScheduledJob,
+    config: &Arc<Config>,
+) -> Result<(), TicketError> {
+    let Some(ticket) = config.tickets.find(job.ticket_id).await? else {
+        return Ok(());
+    };
+
+    match (job.kind, ticket.status) {
+        (JobKind::IdleWarning, TicketStatus::Open) => {
+            let Channel::Guild(channel) = ticket.channel_id().to_channel(http).await? else {
+                return Ok(());
+            };
+            let grace = config.get_idle_grace_hours(channel.guild_id).await?;
+            let close_at = Timestamp::now().unix_timestamp() + grace * 3600;
+
+            channel
+                .send_message(
+                    http,
+                    CreateMessage::new()
+                        .content(ticket.opener_id().mention().to_string())
+                        .embed(
+                            CreateEmbed::new()
+                                .title("Inactive Ticket")
+                                .description(format!(
+                                    "This ticket has had no activity for a while and will be \
+                                    closed <t:{}:R> unless somebody replies.",
+                                    close_at
+                                ))
+                                .color(0xffa500),
+                        ),
+                )
+                .await?;
+            config
+                .tickets
+                .schedule_job(&ticket, JobKind::IdleClose, close_at)
+                .await?;
+        }
+        (JobKind::IdleClose, TicketStatus::Open) => {
+            let bot = http.get_current_user().await?;
+            close_channel(
+                http,
+                ticket.channel_id(),
+                &bot,
+                Some("Closed automatically after a period of inactivity."),
+                config,
+            )
+            .await?;
+        }
+        (JobKind::Close, TicketStatus::PendingClose) => {
+            let closed_by = match job.actor_id {
+                Some(id) => UserId::new(id as u64).to_user(http).await?,
+                None => http.get_current_user().await?.into(),
+            };
+            close_channel(
+                http,
+                ticket.channel_id(),
+                &closed_by,
+                job.reason.as_deref(),
+                config,
+            )
+            .await?;
+        }
+        (JobKind::Close | JobKind::IdleClose, TicketStatus::Closed) => {
+            finish_close(http, &ticket, config).await?;
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+async fn purge_expired_archives(http: &Http, config: &Config) {
+    let now = Timestamp::now().unix_timestamp();
+    let tickets = match config.tickets.list_expired_archives(now).await {
+        Ok(tickets) => tickets,
+        Err(why) => {
+            error!(error = %why, "Error fetching expired archived tickets");
+            return;
+        }
+    };
+
+    for ticket in tickets {
+        if let Err(why) = ticket.channel_id().delete(http).await {
+            warn!(
+                error = %why,
+                ticket_id = ticket.id,
+                "Failed to delete archived ticket channel"
+            );
+            continue;
+        }
+        if let Err(why) = config.tickets.mark_deleted(ticket.channel_id()).await {
+            error!(error = %why, ticket_id = ticket.id, "Error updating deleted ticket channel");
+        }
+    }
+}
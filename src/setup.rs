@@ -0,0 +1,425 @@
+use crate::commands::{
+    configured_channel, format_roles, guild_of, post_panel, save_category, save_log_channel,
+};
+use crate::config::Config;
+use crate::error::TicketError;
+use crate::permissions::{
+    require_bot_guild_permissions, CATEGORY_PERMISSIONS, LOG_CHANNEL_PERMISSIONS, PANEL_PERMISSIONS,
+};
+use crate::response::error_embed;
+use crate::router::CustomId;
+use serenity::all::*;
+use std::sync::Arc;
+use tracing::error;
+
+/// Names of the channels `/ticketsetup` creates when asked to.
+const CATEGORY_NAME: &str = "Tickets";
+const LOG_CHANNEL_NAME: &str = "ticket-logs";
+
+/// The most options Discord lets a select menu have selected, defaults
+/// included; a guild with more support roles only shows the first ones, and
+/// only those can be removed from the wizard.
+const MAX_SELECT_VALUES: u8 = 25;
+
+/// `/ticketsetup`: turns the deferred response into the setup wizard. Every
+/// step is saved right away, so the wizard itself only has to remember the
+/// panel channel, which starts out as the channel it was opened in.
+pub async fn ticket_setup(
+    ctx: &Context,
+    command: &CommandInteraction,
+    config: &Arc<Config>,
+) -> Result<String, TicketError> {
+    let guild_id = guild_of(command)?;
+
+    let (embed, components) = wizard(ctx, guild_id, command.channel_id, config).await?;
+    command
+        .edit_response(
+            &ctx.http,
+            EditInteractionResponse::new()
+                .embed(embed)
+                .components(components),
+        )
+        .await?;
+    Ok("Pick or create what tickets need below, then post the panel.".to_string())
+}
+
+/// Runs a step of the wizard and redraws it in place. Errors are shown to the
+/// admin only; the wizard is redrawn regardless so the menus show what is
+/// actually saved.
+pub async fn run_setup_step(
+    ctx: &Context,
+    component: &ComponentInteraction,
+    id: CustomId,
+    config: &Arc<Config>,
+) {
+    if let Err(why) = component
+        .create_response(&ctx.http, CreateInteractionResponse::Acknowledge)
+        .await
+    {
+        error!(error = %why, "Cannot acknowledge setup step");
+        return;
+    }
+    let Some(guild_id) = component.guild_id else {
+        return;
+    };
+
+    let mut panel_channel =
+        picked_panel_channel(&component.message).unwrap_or(component.channel_id);
+    let step = match id {
+        CustomId::SetupCategory => match selected_channel(component) {
+            Some(channel_id) => save_category(ctx, guild_id, channel_id, config)
+                .await
+                .map(|category| format!("New tickets will be created in {}.", category.mention())),
+            None => Err(TicketError::Validation("Please pick a category.".into())),
+        },
+        CustomId::SetupLogChannel => match selected_channel(component) {
+            Some(channel_id) => save_log_channel(ctx, guild_id, channel_id, config)
+                .await
+                .map(|channel| format!("Ticket actions will be logged in {}.", channel.mention())),
+            None => Err(TicketError::Validation("Please pick a channel.".into())),
+        },
+        CustomId::SetupRoles => set_staff_roles(guild_id, &selected_roles(component), config).await,
+        CustomId::SetupPanelChannel => match selected_channel(component) {
+            Some(channel_id) => {
+                panel_channel = channel_id;
+                Ok(format!(
+                    "The panel will be posted in {}.",
+                    channel_id.mention()
+                ))
+            }
+            None => Err(TicketError::Validation("Please pick a channel.".into())),
+        },
+        CustomId::SetupCreateCategory => create_category(ctx, guild_id, config).await,
+        CustomId::SetupCreateLogChannel => create_log_channel(ctx, guild_id, config).await,
+        CustomId::SetupPostPanel { channel_id } => {
+            let channel_id = ChannelId::new(channel_id as u64);
+            match post_panel(ctx, guild_id, channel_id, config).await {
+                Ok(()) => {
+                    finish(ctx, component, channel_id).await;
+                    return;
+                }
+                Err(e) => Err(e),
+            }
+        }
+        _ => return,
+    };
+
+    let notice = match step {
+        Ok(notice) => notice,
+        Err(e) => {
+            if e.is_internal() {
+                error!(error = %e, "Error running setup step");
+            }
+            show_error(ctx, component, &e).await;
+            String::new()
+        }
+    };
+    let result = match wizard(ctx, guild_id, panel_channel, config).await {
+        Ok((embed, components)) => {
+            component
+                .edit_response(
+                    &ctx.http,
+                    EditInteractionResponse::new()
+                        .content(notice)
+                        .embed(embed)
+                        .components(components),
+                )
+                .await
+        }
+        Err(e) => {
+            error!(error = %e, "Error building setup wizard");
+            return;
+        }
+    };
+    if let Err(why) = result {
+        error!(error = %why, "Cannot update setup wizard");
+    }
+}
+
+/// The wizard: where each setting stands, with a menu to change it and
+/// buttons to create what is missing.
+async fn wizard(
+    ctx: &Context,
+    guild_id: GuildId,
+    panel_channel: ChannelId,
+    config: &Arc<Config>,
+) -> Result<(CreateEmbed, Vec<CreateActionRow>), TicketError> {
+    let category_id = config.get_category_id(guild_id).await?;
+    let log_channel_id = config.get_log_channel_id(guild_id).await?;
+    let roles = config.get_support_roles(guild_id).await?;
+
+    let category = match category_id {
+        Some(id) => configured_channel(ctx, id, CATEGORY_PERMISSIONS).await?,
+        None => "not set".to_string(),
+    };
+    let log_channel = match log_channel_id {
+        Some(id) => configured_channel(ctx, id, LOG_CHANNEL_PERMISSIONS).await?,
+        None => "not set".to_string(),
+    };
+    let panel = configured_channel(ctx, panel_channel.get() as i64, PANEL_PERMISSIONS).await?;
+
+    let embed = CreateEmbed::new()
+        .title("Ticket Setup")
+        .description(format!(
+            "**1. Category:** {}\n**2. Log channel:** {}\n**3. Staff roles:** {}\n\
+            **4. Panel channel:** {}\n\n\
+            Pick existing channels and roles in the menus or let me create the \
+            channels, then post the panel.",
+            category,
+            log_channel,
+            if roles.is_empty() {
+                "none".to_string()
+            } else {
+                format_roles(&roles)
+            },
+            panel
+        ))
+        .color(0x00ff00);
+
+    let category_id = category_id.map(|id| ChannelId::new(id as u64));
+    let log_channel_id = log_channel_id.map(|id| ChannelId::new(id as u64));
+    let components = vec![
+        CreateActionRow::SelectMenu(
+            CreateSelectMenu::new(
+                CustomId::SetupCategory.to_string(),
+                CreateSelectMenuKind::Channel {
+                    channel_types: Some(vec![ChannelType::Category]),
+                    default_channels: category_id.map(|id| vec![id]),
+                },
+            )
+            .placeholder("Category new tickets are created in"),
+        ),
+        CreateActionRow::SelectMenu(
+            CreateSelectMenu::new(
+                CustomId::SetupLogChannel.to_string(),
+                CreateSelectMenuKind::Channel {
+                    channel_types: Some(vec![ChannelType::Text, ChannelType::News]),
+                    default_channels: log_channel_id.map(|id| vec![id]),
+                },
+            )
+            .placeholder("Channel ticket actions are logged in"),
+        ),
+        CreateActionRow::SelectMenu(
+            CreateSelectMenu::new(
+                CustomId::SetupRoles.to_string(),
+                CreateSelectMenuKind::Role {
+                    default_roles: Some(roles.into_iter().take(MAX_SELECT_VALUES.into()).collect()),
+                },
+            )
+            .placeholder("Roles that can see every ticket")
+            .min_values(0)
+            .max_values(MAX_SELECT_VALUES),
+        ),
+        CreateActionRow::SelectMenu(
+            CreateSelectMenu::new(
+                CustomId::SetupPanelChannel.to_string(),
+                CreateSelectMenuKind::Channel {
+                    channel_types: Some(vec![ChannelType::Text, ChannelType::News]),
+                    default_channels: Some(vec![panel_channel]),
+                },
+            )
+            .placeholder("Channel the ticket panel is posted in"),
+        ),
+        CreateActionRow::Buttons(vec![
+            CreateButton::new(CustomId::SetupCreateCategory.to_string())
+                .label("Create Category")
+                .style(ButtonStyle::Secondary)
+                .disabled(category_id.is_some()),
+            CreateButton::new(CustomId::SetupCreateLogChannel.to_string())
+                .label("Create Log Channel")
+                .style(ButtonStyle::Secondary)
+                .disabled(log_channel_id.is_some()),
+            CreateButton::new(
+                CustomId::SetupPostPanel {
+                    channel_id: panel_channel.get() as i64,
+                }
+                .to_string(),
+            )
+            .label("Post Panel")
+            .style(ButtonStyle::Success),
+        ]),
+    ];
+
+    Ok((embed, components))
+}
+
+/// The panel channel picked so far, kept in the custom id of the wizard's
+/// Post Panel button.
+fn picked_panel_channel(message: &Message) -> Option<ChannelId> {
+    message
+        .components
+        .iter()
+        .flat_map(|row| &row.components)
+        .find_map(|component| match component {
+            ActionRowComponent::Button(Button {
+                data: ButtonKind::NonLink { custom_id, .. },
+                ..
+            }) => match CustomId::parse(custom_id)? {
+                CustomId::SetupPostPanel { channel_id } => Some(ChannelId::new(channel_id as u64)),
+                _ => None,
+            },
+            _ => None,
+        })
+}
+
+fn selected_channel(component: &ComponentInteraction) -> Option<ChannelId> {
+    match &component.data.kind {
+        ComponentInteractionDataKind::ChannelSelect { values } => values.first().copied(),
+        _ => None,
+    }
+}
+
+fn selected_roles(component: &ComponentInteraction) -> Vec<RoleId> {
+    match &component.data.kind {
+        ComponentInteractionDataKind::RoleSelect { values } => values.clone(),
+        _ => Vec::new(),
+    }
+}
+
+/// Makes `selected` the guild's support roles, adding and removing roles as
+/// needed. Only the roles the menu showed can be removed; past the first
+/// [`MAX_SELECT_VALUES`] roles are kept as they are.
+async fn set_staff_roles(
+    guild_id: GuildId,
+    selected: &[RoleId],
+    config: &Arc<Config>,
+) -> Result<String, TicketError> {
+    let current = config.get_support_roles(guild_id).await?;
+    let (shown, hidden) = current.split_at(current.len().min(MAX_SELECT_VALUES.into()));
+    for role_id in shown.iter().filter(|role_id| !selected.contains(role_id)) {
+        config.remove_support_role(guild_id, *role_id).await?;
+    }
+    for role_id in selected.iter().filter(|role_id| !current.contains(role_id)) {
+        config.add_support_role(guild_id, *role_id).await?;
+    }
+
+    if !hidden.is_empty() {
+        Ok(format!(
+            "Staff roles updated. The {} roles past the first {} were left as they are; \
+            see them with `/ticketconfig roles list`.",
+            hidden.len(),
+            MAX_SELECT_VALUES
+        ))
+    } else if selected.is_empty() {
+        Ok("No staff roles, only admins see every ticket.".to_string())
+    } else {
+        Ok(format!(
+            "{} can now see every ticket.",
+            format_roles(selected)
+        ))
+    }
+}
+
+/// Creates a category hidden from everybody but the bot and makes it the
+/// ticket category.
+async fn create_category(
+    ctx: &Context,
+    guild_id: GuildId,
+    config: &Arc<Config>,
+) -> Result<String, TicketError> {
+    require_bot_guild_permissions(ctx, guild_id, Permissions::MANAGE_CHANNELS).await?;
+    let bot = ctx.http.get_current_user().await?;
+
+    let category = guild_id
+        .create_channel(
+            &ctx.http,
+            CreateChannel::new(CATEGORY_NAME)
+                .kind(ChannelType::Category)
+                .permissions(vec![
+                    hidden_from_everyone(guild_id),
+                    PermissionOverwrite {
+                        allow: CATEGORY_PERMISSIONS,
+                        deny: Permissions::empty(),
+                        kind: PermissionOverwriteType::Member(bot.id),
+                    },
+                ]),
+        )
+        .await?;
+    let category = save_category(ctx, guild_id, category.id, config).await?;
+    Ok(format!("Created {} for new tickets.", category.mention()))
+}
+
+/// Creates a log channel only the bot and the staff roles can see, in the
+/// ticket category when there is one, and makes it the log channel.
+async fn create_log_channel(
+    ctx: &Context,
+    guild_id: GuildId,
+    config: &Arc<Config>,
+) -> Result<String, TicketError> {
+    require_bot_guild_permissions(ctx, guild_id, Permissions::MANAGE_CHANNELS).await?;
+    let bot = ctx.http.get_current_user().await?;
+
+    let mut permissions = vec![
+        hidden_from_everyone(guild_id),
+        PermissionOverwrite {
+            allow: LOG_CHANNEL_PERMISSIONS | Permissions::READ_MESSAGE_HISTORY,
+            deny: Permissions::empty(),
+            kind: PermissionOverwriteType::Member(bot.id),
+        },
+    ];
+    permissions.extend(
+        config
+            .get_support_roles(guild_id)
+            .await?
+            .into_iter()
+            .map(|role_id| PermissionOverwrite {
+                allow: Permissions::VIEW_CHANNEL | Permissions::READ_MESSAGE_HISTORY,
+                deny: Permissions::empty(),
+                kind: PermissionOverwriteType::Role(role_id),
+            }),
+    );
+
+    let mut builder = CreateChannel::new(LOG_CHANNEL_NAME)
+        .kind(ChannelType::Text)
+        .permissions(permissions);
+    if let Some(category_id) = config.get_category_id(guild_id).await? {
+        builder = builder.category(ChannelId::new(category_id as u64));
+    }
+
+    let channel = guild_id.create_channel(&ctx.http, builder).await?;
+    let channel = save_log_channel(ctx, guild_id, channel.id, config).await?;
+    Ok(format!("Created {} for ticket logs.", channel.mention()))
+}
+
+fn hidden_from_everyone(guild_id: GuildId) -> PermissionOverwrite {
+    PermissionOverwrite {
+        allow: Permissions::empty(),
+        deny: Permissions::VIEW_CHANNEL,
+        kind: PermissionOverwriteType::Role(guild_id.everyone_role()),
+    }
+}
+
+/// Replaces the wizard with a summary once the panel is up.
+async fn finish(ctx: &Context, component: &ComponentInteraction, panel_channel: ChannelId) {
+    let content = format!(
+        "Tickets are set up and the panel is up in {}. Use `/ticketconfig show` to review \
+        everything or `/ticketsetup` to change it.",
+        panel_channel.mention()
+    );
+    if let Err(why) = component
+        .edit_response(
+            &ctx.http,
+            EditInteractionResponse::new()
+                .content(content)
+                .embeds(Vec::new())
+                .components(Vec::new()),
+        )
+        .await
+    {
+        error!(error = %why, "Cannot update setup wizard");
+    }
+}
+
+async fn show_error(ctx: &Context, component: &ComponentInteraction, error: &TicketError) {
+    if let Err(why) = component
+        .create_followup(
+            &ctx.http,
+            CreateInteractionResponseFollowup::new()
+                .embed(error_embed(error))
+                .ephemeral(true),
+        )
+        .await
+    {
+        error!(error = %why, "Cannot respond to setup step");
+    }
+}
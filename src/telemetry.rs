@@ -0,0 +1,104 @@
+use std::{env, fs};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer};
+
+/// File read for logging settings, next to the database. Lines look like
+/// `level = debug`; the `LOG_LEVEL`, `LOG_FORMAT` and `LOG_DIR` environment
+/// variables take precedence over it.
+const SETTINGS_FILE: &str = "logging.conf";
+
+struct Settings {
+    /// `tracing` filter directives, e.g. `info` or `axyl_tickets=debug,warn`.
+    level: String,
+    /// Emit one JSON object per line instead of human readable text.
+    json: bool,
+    /// Directory for a daily rolling log file, none to log to stdout only.
+    dir: Option<String>,
+}
+
+impl Settings {
+    fn load() -> Self {
+        let mut settings = Settings {
+            level: "info".to_string(),
+            json: false,
+            dir: None,
+        };
+
+        if let Ok(contents) = fs::read_to_string(SETTINGS_FILE) {
+            for line in contents.lines() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                if let Some((key, value)) = line.split_once('=') {
+                    settings.set(key.trim(), value.trim());
+                }
+            }
+        }
+
+        for (key, var) in [
+            ("level", "LOG_LEVEL"),
+            ("format", "LOG_FORMAT"),
+            ("dir", "LOG_DIR"),
+        ] {
+            if let Ok(value) = env::var(var) {
+                settings.set(key, &value);
+            }
+        }
+
+        settings
+    }
+
+    fn set(&mut self, key: &str, value: &str) {
+        match key {
+            "level" => self.level = value.to_string(),
+            "format" => self.json = value.eq_ignore_ascii_case("json"),
+            "dir" if !value.is_empty() => self.dir = Some(value.to_string()),
+            _ => {}
+        }
+    }
+}
+
+fn format_layer<S, W>(json: bool, ansi: bool, writer: W) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+    W: for<'w> fmt::MakeWriter<'w> + Send + Sync + 'static,
+{
+    if json {
+        fmt::layer()
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .with_writer(writer)
+            .boxed()
+    } else {
+        fmt::layer().with_ansi(ansi).with_writer(writer).boxed()
+    }
+}
+
+/// Installs the global subscriber. The returned guard flushes the log file
+/// when dropped, so keep it alive until the bot shuts down.
+pub fn init() -> Option<WorkerGuard> {
+    let settings = Settings::load();
+    let filter = EnvFilter::try_new(&settings.level).unwrap_or_else(|_| EnvFilter::new("info"));
+
+    let (file_layer, guard) = match &settings.dir {
+        Some(dir) => {
+            let appender = tracing_appender::rolling::daily(dir, "axyl_tickets.log");
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            (
+                Some(format_layer(settings.json, false, writer)),
+                Some(guard),
+            )
+        }
+        None => (None, None),
+    };
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(format_layer(settings.json, true, std::io::stdout))
+        .with(file_layer)
+        .init();
+
+    guard
+}
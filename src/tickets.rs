@@ -0,0 +1,1001 @@
+use crate::config::DEFAULT_ARCHIVE_RETENTION_HOURS;
+use crate::transcript::Transcript;
+use serenity::all::{ChannelId, GuildId, Timestamp, UserId};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard};
+
+/// Lifecycle of a ticket: `Open -> PendingClose -> Closed -> Deleted`, with
+/// `PendingClose -> Open` when a scheduled close is cancelled and
+/// `Closed -> Open` when an archived ticket is reopened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(rename_all = "snake_case")]
+pub enum TicketStatus {
+    Open,
+    PendingClose,
+    Closed,
+    Deleted,
+}
+
+impl TicketStatus {
+    pub fn can_transition_to(self, next: TicketStatus) -> bool {
+        matches!(
+            (self, next),
+            (TicketStatus::Open, TicketStatus::PendingClose)
+                | (TicketStatus::Open, TicketStatus::Closed)
+                | (TicketStatus::PendingClose, TicketStatus::Open)
+                | (TicketStatus::PendingClose, TicketStatus::Closed)
+                | (TicketStatus::Closed, TicketStatus::Open)
+                | (TicketStatus::Open, TicketStatus::Deleted)
+                | (TicketStatus::PendingClose, TicketStatus::Deleted)
+                | (TicketStatus::Closed, TicketStatus::Deleted)
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Ticket {
+    pub id: i64,
+    pub channel_id: i64,
+    pub opener_id: i64,
+    pub created_at: i64,
+    pub type_id: Option<i64>,
+    pub number: Option<i64>,
+    pub claimed_by: Option<i64>,
+    pub escalation_level: i64,
+    pub status: TicketStatus,
+}
+
+impl Ticket {
+    pub fn channel_id(&self) -> ChannelId {
+        ChannelId::new(self.channel_id as u64)
+    }
+
+    pub fn opener_id(&self) -> UserId {
+        UserId::new(self.opener_id as u64)
+    }
+
+    pub fn claimed_by(&self) -> Option<UserId> {
+        self.claimed_by.map(|id| UserId::new(id as u64))
+    }
+}
+
+/// Where an archived ticket channel lived before it was archived.
+#[derive(Debug, Clone)]
+pub struct ArchivedFrom {
+    pub category_id: Option<i64>,
+    pub name: Option<String>,
+}
+
+impl ArchivedFrom {
+    pub fn category_id(&self) -> Option<ChannelId> {
+        self.category_id.map(|id| ChannelId::new(id as u64))
+    }
+}
+
+/// Something the scheduler has to do to a ticket at a given time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(rename_all = "snake_case")]
+pub enum JobKind {
+    /// Warn that the ticket is about to be closed for inactivity.
+    IdleWarning,
+    /// Close the ticket after the warning went unanswered.
+    IdleClose,
+    /// Close the ticket once the delay of `/ticket close` or the close button
+    /// ran out.
+    Close,
+}
+
+#[derive(Debug, Clone)]
+pub struct ScheduledJob {
+    pub id: i64,
+    pub ticket_id: i64,
+    pub kind: JobKind,
+    pub run_at: i64,
+    /// Who asked for the job, for jobs that act on somebody's behalf.
+    pub actor_id: Option<i64>,
+    pub reason: Option<String>,
+}
+
+/// Something that happened to a ticket, as kept in its audit log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(rename_all = "snake_case")]
+pub enum TicketEventKind {
+    Opened,
+    CloseScheduled,
+    CloseCancelled,
+    Closed,
+    Reopened,
+    UserAdded,
+    UserRemoved,
+    Claimed,
+    Unclaimed,
+    Transferred,
+    Escalated,
+    Renamed,
+}
+
+impl TicketEventKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            TicketEventKind::Opened => "Opened",
+            TicketEventKind::CloseScheduled => "Close Scheduled",
+            TicketEventKind::CloseCancelled => "Close Cancelled",
+            TicketEventKind::Closed => "Closed",
+            TicketEventKind::Reopened => "Reopened",
+            TicketEventKind::UserAdded => "User Added",
+            TicketEventKind::UserRemoved => "User Removed",
+            TicketEventKind::Claimed => "Claimed",
+            TicketEventKind::Unclaimed => "Unclaimed",
+            TicketEventKind::Transferred => "Transferred",
+            TicketEventKind::Escalated => "Escalated",
+            TicketEventKind::Renamed => "Renamed",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TicketEvent {
+    /// Channel of the ticket the event belongs to.
+    pub channel_id: i64,
+    pub kind: TicketEventKind,
+    pub actor_id: i64,
+    /// The member the event was about, e.g. the one added or transferred to.
+    pub target_id: Option<i64>,
+    /// Event specific details as a JSON object, e.g. the close reason.
+    pub payload: String,
+    pub created_at: i64,
+}
+
+impl TicketEvent {
+    pub fn channel_id(&self) -> ChannelId {
+        ChannelId::new(self.channel_id as u64)
+    }
+
+    pub fn actor_id(&self) -> UserId {
+        UserId::new(self.actor_id as u64)
+    }
+
+    pub fn target_id(&self) -> Option<UserId> {
+        self.target_id.map(|id| UserId::new(id as u64))
+    }
+
+    pub fn payload(&self) -> serde_json::Map<String, serde_json::Value> {
+        serde_json::from_str(&self.payload).unwrap_or_default()
+    }
+}
+
+type OpeningLock = Arc<AsyncMutex<()>>;
+
+pub struct TicketService {
+    db: Arc<SqlitePool>,
+    /// One lock per member and guild, held from the limit check until their
+    /// new ticket is recorded.
+    opening: Mutex<HashMap<(GuildId, UserId), OpeningLock>>,
+}
+
+impl TicketService {
+    pub fn new(db: Arc<SqlitePool>) -> Self {
+        Self {
+            db,
+            opening: Mutex::default(),
+        }
+    }
+
+    /// Waits until `user_id` is not opening another ticket in `guild_id` and
+    /// keeps others from doing so until the guard is dropped, so rapid
+    /// repeated clicks cannot all pass the limit check before the first
+    /// ticket exists.
+    pub async fn lock_opening(&self, guild_id: GuildId, user_id: UserId) -> OwnedMutexGuard<()> {
+        let lock = {
+            let mut locks = self.opening.lock().expect("ticket lock map poisoned");
+            // Drop locks nobody holds or waits for.
+            locks.retain(|_, lock| Arc::strong_count(lock) > 1);
+            Arc::clone(locks.entry((guild_id, user_id)).or_default())
+        };
+        lock.lock_owned().await
+    }
+
+    pub async fn open(
+        &self,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        opener_id: UserId,
+        type_id: Option<i64>,
+        number: i64,
+    ) -> Result<Ticket, sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        let channel_id = channel_id.get() as i64;
+        let opener_id = opener_id.get() as i64;
+        let now = Timestamp::now().unix_timestamp();
+
+        sqlx::query_as!(
+            Ticket,
+            r#"INSERT INTO tickets
+                (guild_id, channel_id, opener_id, created_at, type_id, number, status)
+            VALUES (?, ?, ?, ?, ?, ?, 'open')
+            RETURNING id, channel_id, opener_id, created_at, type_id, number, claimed_by, escalation_level, status AS "status: TicketStatus""#,
+            guild_id,
+            channel_id,
+            opener_id,
+            now,
+            type_id,
+            number
+        )
+        .fetch_one(&*self.db)
+        .await
+    }
+
+    pub async fn find(&self, id: i64) -> Result<Option<Ticket>, sqlx::Error> {
+        sqlx::query_as!(
+            Ticket,
+            r#"SELECT id AS "id!", channel_id, opener_id, created_at, type_id, number, claimed_by,
+                escalation_level, status AS "status: TicketStatus"
+            FROM tickets WHERE id = ?"#,
+            id
+        )
+        .fetch_optional(&*self.db)
+        .await
+    }
+
+    /// Returns the most recent ticket that lives in `channel_id`, in any state.
+    pub async fn find_by_channel(
+        &self,
+        channel_id: ChannelId,
+    ) -> Result<Option<Ticket>, sqlx::Error> {
+        let channel_id = channel_id.get() as i64;
+        sqlx::query_as!(
+            Ticket,
+            r#"SELECT id AS "id!", channel_id, opener_id, created_at, type_id, number, claimed_by, escalation_level, status AS "status: TicketStatus"
+            FROM tickets WHERE channel_id = ? ORDER BY id DESC LIMIT 1"#,
+            channel_id
+        )
+        .fetch_optional(&*self.db)
+        .await
+    }
+
+    /// Tickets in `guild_id` whose channel still exists and has not been closed.
+    pub async fn list_open(&self, guild_id: GuildId) -> Result<Vec<Ticket>, sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        sqlx::query_as!(
+            Ticket,
+            r#"SELECT id AS "id!", channel_id, opener_id, created_at, type_id, number, claimed_by, escalation_level, status AS "status: TicketStatus"
+            FROM tickets WHERE guild_id = ? AND status IN ('open', 'pending_close')
+            ORDER BY id"#,
+            guild_id
+        )
+        .fetch_all(&*self.db)
+        .await
+    }
+
+    /// Open tickets of `opener_id` in `guild_id`, optionally only of one type.
+    pub async fn list_open_for_user(
+        &self,
+        guild_id: GuildId,
+        opener_id: UserId,
+        type_id: Option<i64>,
+    ) -> Result<Vec<Ticket>, sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        let opener_id = opener_id.get() as i64;
+        sqlx::query_as!(
+            Ticket,
+            r#"SELECT id AS "id!", channel_id, opener_id, created_at, type_id, number, claimed_by, escalation_level,
+                status AS "status: TicketStatus"
+            FROM tickets
+            WHERE guild_id = ? AND opener_id = ? AND status IN ('open', 'pending_close')
+                AND (?3 IS NULL OR type_id = ?3)
+            ORDER BY id"#,
+            guild_id,
+            opener_id,
+            type_id
+        )
+        .fetch_all(&*self.db)
+        .await
+    }
+
+    /// When `opener_id` last opened a ticket in `guild_id`.
+    pub async fn last_opened_at(
+        &self,
+        guild_id: GuildId,
+        opener_id: UserId,
+    ) -> Result<Option<i64>, sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        let opener_id = opener_id.get() as i64;
+        sqlx::query_scalar!(
+            "SELECT MAX(created_at) FROM tickets WHERE guild_id = ? AND opener_id = ?",
+            guild_id,
+            opener_id
+        )
+        .fetch_one(&*self.db)
+        .await
+    }
+
+    /// Tickets in `guild_id` with an intake form answer containing `query`.
+    pub async fn search_answers(
+        &self,
+        guild_id: GuildId,
+        query: &str,
+    ) -> Result<Vec<Ticket>, sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        let pattern = format!(
+            "%{}%",
+            query
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+        sqlx::query_as!(
+            Ticket,
+            r#"SELECT DISTINCT t.id AS "id!", t.channel_id, t.opener_id, t.created_at, t.type_id, t.number, t.claimed_by, t.escalation_level,
+                t.status AS "status: TicketStatus"
+            FROM tickets t JOIN ticket_answers a ON a.ticket_id = t.id
+            WHERE t.guild_id = ? AND a.answer LIKE ? ESCAPE '\'
+            ORDER BY t.id DESC LIMIT 25"#,
+            guild_id,
+            pattern
+        )
+        .fetch_all(&*self.db)
+        .await
+    }
+
+    /// Moves `ticket` to `next` if the lifecycle allows it and nobody else has
+    /// changed the row in the meantime. Returns whether the row was updated.
+    pub async fn set_status(
+        &self,
+        ticket: &Ticket,
+        next: TicketStatus,
+    ) -> Result<bool, sqlx::Error> {
+        if !ticket.status.can_transition_to(next) {
+            return Ok(false);
+        }
+
+        let result = sqlx::query!(
+            "UPDATE tickets SET status = ? WHERE id = ? AND status = ?",
+            next,
+            ticket.id,
+            ticket.status
+        )
+        .execute(&*self.db)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn close(
+        &self,
+        ticket: &Ticket,
+        closed_by: UserId,
+        reason: Option<&str>,
+    ) -> Result<bool, sqlx::Error> {
+        if !ticket.status.can_transition_to(TicketStatus::Closed) {
+            return Ok(false);
+        }
+
+        let closed_by = closed_by.get() as i64;
+        let now = Timestamp::now().unix_timestamp();
+        let result = sqlx::query!(
+            "UPDATE tickets SET status = 'closed', closed_at = ?, closed_by = ?, close_reason = ?
+            WHERE id = ? AND status = ?",
+            now,
+            closed_by,
+            reason,
+            ticket.id,
+            ticket.status
+        )
+        .execute(&*self.db)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Remembers the category and name `ticket`'s channel had before it was
+    /// archived so reopening can restore them.
+    pub async fn record_archive(
+        &self,
+        ticket: &Ticket,
+        category_id: Option<ChannelId>,
+        name: &str,
+    ) -> Result<(), sqlx::Error> {
+        let category_id = category_id.map(|id| id.get() as i64);
+        sqlx::query!(
+            "UPDATE tickets SET archived_from_category_id = ?, archived_from_name = ? WHERE id = ?",
+            category_id,
+            name,
+            ticket.id
+        )
+        .execute(&*self.db)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn archived_from(&self, ticket: &Ticket) -> Result<ArchivedFrom, sqlx::Error> {
+        sqlx::query_as!(
+            ArchivedFrom,
+            r#"SELECT archived_from_category_id AS category_id, archived_from_name AS name
+            FROM tickets WHERE id = ?"#,
+            ticket.id
+        )
+        .fetch_one(&*self.db)
+        .await
+    }
+
+    /// Moves an archived ticket back to open and forgets how it was closed.
+    /// Returns whether the row was updated.
+    pub async fn reopen(&self, ticket: &Ticket) -> Result<bool, sqlx::Error> {
+        if !ticket.status.can_transition_to(TicketStatus::Open) {
+            return Ok(false);
+        }
+
+        let result = sqlx::query!(
+            "UPDATE tickets SET status = 'open', closed_at = NULL, closed_by = NULL,
+                close_reason = NULL, archived_from_category_id = NULL, archived_from_name = NULL
+            WHERE id = ? AND status = ?",
+            ticket.id,
+            ticket.status
+        )
+        .execute(&*self.db)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Archived tickets whose channel has outlived the retention period of its
+    /// guild, as of `now`. Closed tickets whose channel never got archived are
+    /// left to their close job.
+    pub async fn list_expired_archives(&self, now: i64) -> Result<Vec<Ticket>, sqlx::Error> {
+        let default_hours = DEFAULT_ARCHIVE_RETENTION_HOURS;
+        sqlx::query_as!(
+            Ticket,
+            r#"SELECT t.id AS "id!", t.channel_id, t.opener_id, t.created_at, t.type_id, t.number, t.claimed_by, t.escalation_level,
+                t.status AS "status: TicketStatus"
+            FROM tickets t LEFT JOIN guild_configs g ON g.guild_id = t.guild_id
+            WHERE t.status = 'closed' AND t.closed_at IS NOT NULL
+                AND t.archived_from_name IS NOT NULL
+                AND COALESCE(g.archive_retention_hours, ?1) > 0
+                AND t.closed_at + COALESCE(g.archive_retention_hours, ?1) * 3600 <= ?2
+            ORDER BY t.id"#,
+            default_hours,
+            now
+        )
+        .fetch_all(&*self.db)
+        .await
+    }
+
+    /// Assigns an unclaimed, open ticket to `staff_id`. Returns whether the row
+    /// was updated, i.e. false if somebody else got there first.
+    pub async fn claim(&self, ticket: &Ticket, staff_id: UserId) -> Result<bool, sqlx::Error> {
+        let staff_id = staff_id.get() as i64;
+        let now = Timestamp::now().unix_timestamp();
+        let result = sqlx::query!(
+            "UPDATE tickets SET claimed_by = ?, claimed_at = ?
+            WHERE id = ? AND claimed_by IS NULL AND status = 'open'",
+            staff_id,
+            now,
+            ticket.id
+        )
+        .execute(&*self.db)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn unclaim(&self, ticket: &Ticket) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            "UPDATE tickets SET claimed_by = NULL, claimed_at = NULL
+            WHERE id = ? AND claimed_by IS NOT NULL",
+            ticket.id
+        )
+        .execute(&*self.db)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Hands `ticket` over to `to_id` and records who did it.
+    pub async fn transfer(
+        &self,
+        ticket: &Ticket,
+        to_id: UserId,
+        transferred_by: UserId,
+    ) -> Result<(), sqlx::Error> {
+        let to_id = to_id.get() as i64;
+        let transferred_by = transferred_by.get() as i64;
+        let now = Timestamp::now().unix_timestamp();
+        let mut tx = self.db.begin().await?;
+
+        sqlx::query!(
+            "INSERT INTO ticket_transfers (ticket_id, from_id, to_id, transferred_by, created_at)
+            VALUES (?, ?, ?, ?, ?)",
+            ticket.id,
+            ticket.claimed_by,
+            to_id,
+            transferred_by,
+            now
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            "UPDATE tickets SET claimed_by = ?, claimed_at = ? WHERE id = ?",
+            to_id,
+            now,
+            ticket.id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await
+    }
+
+    /// Raises `ticket` to escalation tier `level` and adds the step to its
+    /// history. Returns false if the ticket was escalated in the meantime.
+    pub async fn escalate(
+        &self,
+        ticket: &Ticket,
+        level: i64,
+        escalated_by: UserId,
+        reason: Option<&str>,
+    ) -> Result<bool, sqlx::Error> {
+        let escalated_by = escalated_by.get() as i64;
+        let now = Timestamp::now().unix_timestamp();
+        let mut tx = self.db.begin().await?;
+
+        let result = sqlx::query!(
+            "UPDATE tickets SET escalation_level = ? WHERE id = ? AND escalation_level = ?",
+            level,
+            ticket.id,
+            ticket.escalation_level
+        )
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        sqlx::query!(
+            "INSERT INTO ticket_escalations (ticket_id, level, escalated_by, reason, created_at)
+            VALUES (?, ?, ?, ?, ?)",
+            ticket.id,
+            level,
+            escalated_by,
+            reason,
+            now
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    pub async fn record_activity(&self, ticket: &Ticket, at: i64) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "UPDATE tickets SET last_activity_at = ? WHERE id = ?",
+            at,
+            ticket.id
+        )
+        .execute(&*self.db)
+        .await?;
+        Ok(())
+    }
+
+    /// Schedules a `kind` job for `ticket`, replacing any earlier one.
+    pub async fn schedule_job(
+        &self,
+        ticket: &Ticket,
+        kind: JobKind,
+        run_at: i64,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "INSERT INTO scheduled_jobs (ticket_id, kind, run_at) VALUES (?, ?, ?)
+            ON CONFLICT (ticket_id, kind) DO UPDATE SET run_at = excluded.run_at, attempts = 0",
+            ticket.id,
+            kind,
+            run_at
+        )
+        .execute(&*self.db)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn cancel_job(&self, ticket: &Ticket, kind: JobKind) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "DELETE FROM scheduled_jobs WHERE ticket_id = ? AND kind = ?",
+            ticket.id,
+            kind
+        )
+        .execute(&*self.db)
+        .await?;
+        Ok(())
+    }
+
+    /// Schedules `ticket` to be closed by `closed_by` at `run_at` and returns
+    /// the job, whose id the cancel button refers to.
+    pub async fn schedule_close(
+        &self,
+        ticket: &Ticket,
+        run_at: i64,
+        closed_by: UserId,
+        reason: Option<&str>,
+    ) -> Result<ScheduledJob, sqlx::Error> {
+        let closed_by = closed_by.get() as i64;
+        sqlx::query_as!(
+            ScheduledJob,
+            r#"INSERT INTO scheduled_jobs (ticket_id, kind, run_at, actor_id, reason)
+            VALUES (?, 'close', ?, ?, ?)
+            ON CONFLICT (ticket_id, kind) DO UPDATE SET
+                run_at = excluded.run_at,
+                actor_id = excluded.actor_id,
+                reason = excluded.reason,
+                attempts = 0
+            RETURNING id, ticket_id, kind AS "kind: JobKind", run_at, actor_id, reason"#,
+            ticket.id,
+            run_at,
+            closed_by,
+            reason
+        )
+        .fetch_one(&*self.db)
+        .await
+    }
+
+    /// Deletes the job with `id`. Returns the ticket it belonged to, or `None`
+    /// if it already ran or was cancelled.
+    pub async fn cancel_job_by_id(&self, id: i64) -> Result<Option<i64>, sqlx::Error> {
+        sqlx::query_scalar!(
+            "DELETE FROM scheduled_jobs WHERE id = ? RETURNING ticket_id",
+            id
+        )
+        .fetch_optional(&*self.db)
+        .await
+    }
+
+    /// Every job of `kind` that has not run yet.
+    pub async fn pending_jobs(&self, kind: JobKind) -> Result<Vec<ScheduledJob>, sqlx::Error> {
+        sqlx::query_as!(
+            ScheduledJob,
+            r#"SELECT id AS "id!", ticket_id, kind AS "kind: JobKind", run_at, actor_id, reason
+            FROM scheduled_jobs WHERE kind = ? ORDER BY run_at"#,
+            kind
+        )
+        .fetch_all(&*self.db)
+        .await
+    }
+
+    /// Jobs whose time has come as of `now`, oldest first.
+    pub async fn due_jobs(&self, now: i64) -> Result<Vec<ScheduledJob>, sqlx::Error> {
+        sqlx::query_as!(
+            ScheduledJob,
+            r#"SELECT id AS "id!", ticket_id, kind AS "kind: JobKind", run_at, actor_id, reason
+            FROM scheduled_jobs WHERE run_at <= ? ORDER BY run_at"#,
+            now
+        )
+        .fetch_all(&*self.db)
+        .await
+    }
+
+    /// Claims `job` so it runs only once at a time, by moving it to when it
+    /// should be retried should this run fail or be cut short by a restart.
+    /// Retries back off from a minute up to a day. Returns the new run time,
+    /// or `None` if the job was already claimed, cancelled or rescheduled.
+    pub async fn claim_job(
+        &self,
+        job: &ScheduledJob,
+        now: i64,
+    ) -> Result<Option<i64>, sqlx::Error> {
+        sqlx::query_scalar!(
+            "UPDATE scheduled_jobs
+            SET run_at = ?1 + MIN(60 << MIN(attempts, 16), 86400), attempts = attempts + 1
+            WHERE id = ?2 AND run_at = ?3
+            RETURNING run_at",
+            now,
+            job.id,
+            job.run_at
+        )
+        .fetch_optional(&*self.db)
+        .await
+    }
+
+    /// Removes a job claimed with [`claim_job`](Self::claim_job) once it ran,
+    /// unless it was rescheduled in the meantime.
+    pub async fn finish_job(
+        &self,
+        job: &ScheduledJob,
+        claimed_until: i64,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "DELETE FROM scheduled_jobs WHERE id = ? AND run_at = ?",
+            job.id,
+            claimed_until
+        )
+        .execute(&*self.db)
+        .await?;
+        Ok(())
+    }
+
+    /// Schedules an inactivity warning for every open ticket in `guild_id`,
+    /// `idle_secs` after its last message or after `now` if it has none, and
+    /// drops pending inactivity closes so each ticket gets warned first.
+    pub async fn schedule_idle_warnings(
+        &self,
+        guild_id: GuildId,
+        now: i64,
+        idle_secs: i64,
+    ) -> Result<(), sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        let mut tx = self.db.begin().await?;
+        sqlx::query!(
+            "DELETE FROM scheduled_jobs WHERE kind = 'idle_close'
+                AND ticket_id IN (SELECT id FROM tickets WHERE guild_id = ?)",
+            guild_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "INSERT INTO scheduled_jobs (ticket_id, kind, run_at)
+            SELECT id, 'idle_warning', COALESCE(last_activity_at, ?1) + ?2
+            FROM tickets WHERE guild_id = ?3 AND status = 'open'
+            ON CONFLICT (ticket_id, kind) DO UPDATE SET run_at = excluded.run_at, attempts = 0",
+            now,
+            idle_secs,
+            guild_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await
+    }
+
+    /// Drops every inactivity warning and close of the tickets in `guild_id`.
+    pub async fn cancel_idle_jobs(&self, guild_id: GuildId) -> Result<(), sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        sqlx::query!(
+            "DELETE FROM scheduled_jobs WHERE kind IN ('idle_warning', 'idle_close')
+                AND ticket_id IN (SELECT id FROM tickets WHERE guild_id = ?)",
+            guild_id
+        )
+        .execute(&*self.db)
+        .await?;
+        Ok(())
+    }
+
+    /// Marks whatever ticket lives in `channel_id` as deleted, e.g. after the
+    /// channel was removed by the bot or by hand.
+    pub async fn mark_deleted(&self, channel_id: ChannelId) -> Result<(), sqlx::Error> {
+        let channel_id = channel_id.get() as i64;
+        sqlx::query!(
+            "UPDATE tickets SET status = 'deleted' WHERE channel_id = ? AND status != 'deleted'",
+            channel_id
+        )
+        .execute(&*self.db)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn add_participant(
+        &self,
+        ticket: &Ticket,
+        user_id: UserId,
+        added_by: UserId,
+    ) -> Result<(), sqlx::Error> {
+        let user_id = user_id.get() as i64;
+        let added_by = added_by.get() as i64;
+        let now = Timestamp::now().unix_timestamp();
+        sqlx::query!(
+            "INSERT OR REPLACE INTO ticket_participants (ticket_id, user_id, added_by, added_at)
+            VALUES (?, ?, ?, ?)",
+            ticket.id,
+            user_id,
+            added_by,
+            now
+        )
+        .execute(&*self.db)
+        .await?;
+        Ok(())
+    }
+
+    /// Members added to `ticket` besides its opener.
+    pub async fn list_participants(&self, ticket: &Ticket) -> Result<Vec<UserId>, sqlx::Error> {
+        let rows = sqlx::query_scalar!(
+            "SELECT user_id FROM ticket_participants WHERE ticket_id = ? ORDER BY added_at",
+            ticket.id
+        )
+        .fetch_all(&*self.db)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|user_id| UserId::new(user_id as u64))
+            .collect())
+    }
+
+    pub async fn is_participant(
+        &self,
+        ticket: &Ticket,
+        user_id: UserId,
+    ) -> Result<bool, sqlx::Error> {
+        let user_id = user_id.get() as i64;
+        let row = sqlx::query_scalar!(
+            "SELECT user_id FROM ticket_participants WHERE ticket_id = ? AND user_id = ?",
+            ticket.id,
+            user_id
+        )
+        .fetch_optional(&*self.db)
+        .await?;
+        Ok(row.is_some())
+    }
+
+    pub async fn remove_participant(
+        &self,
+        ticket: &Ticket,
+        user_id: UserId,
+    ) -> Result<(), sqlx::Error> {
+        let user_id = user_id.get() as i64;
+        sqlx::query!(
+            "DELETE FROM ticket_participants WHERE ticket_id = ? AND user_id = ?",
+            ticket.id,
+            user_id
+        )
+        .execute(&*self.db)
+        .await?;
+        Ok(())
+    }
+
+    /// Adds an entry to `ticket`'s audit log. `payload` should be a JSON
+    /// object; anything else is stored as an empty one.
+    pub async fn record_event(
+        &self,
+        ticket: &Ticket,
+        kind: TicketEventKind,
+        actor_id: UserId,
+        target_id: Option<UserId>,
+        payload: serde_json::Value,
+    ) -> Result<(), sqlx::Error> {
+        let actor_id = actor_id.get() as i64;
+        let target_id = target_id.map(|id| id.get() as i64);
+        let payload = match payload {
+            serde_json::Value::Object(_) => payload.to_string(),
+            _ => "{}".to_string(),
+        };
+        let now = Timestamp::now().unix_timestamp();
+        sqlx::query!(
+            "INSERT INTO ticket_events (ticket_id, kind, actor_id, target_id, payload, created_at)
+            VALUES (?, ?, ?, ?, ?, ?)",
+            ticket.id,
+            kind,
+            actor_id,
+            target_id,
+            payload,
+            now
+        )
+        .execute(&*self.db)
+        .await?;
+        Ok(())
+    }
+
+    /// One page of `ticket`'s audit log, oldest first.
+    pub async fn list_events(
+        &self,
+        ticket: &Ticket,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<TicketEvent>, sqlx::Error> {
+        sqlx::query_as!(
+            TicketEvent,
+            r#"SELECT t.channel_id, e.kind AS "kind: TicketEventKind",
+                e.actor_id, e.target_id, e.payload, e.created_at
+            FROM ticket_events e JOIN tickets t ON t.id = e.ticket_id
+            WHERE e.ticket_id = ?
+            ORDER BY e.id LIMIT ? OFFSET ?"#,
+            ticket.id,
+            limit,
+            offset
+        )
+        .fetch_all(&*self.db)
+        .await
+    }
+
+    pub async fn count_events(&self, ticket: &Ticket) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count: i64" FROM ticket_events WHERE ticket_id = ?"#,
+            ticket.id
+        )
+        .fetch_one(&*self.db)
+        .await
+    }
+
+    /// One page of the events in `guild_id` that `user_id` took part in, as
+    /// either the actor or the target, newest first.
+    pub async fn list_user_events(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<TicketEvent>, sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        let user_id = user_id.get() as i64;
+        sqlx::query_as!(
+            TicketEvent,
+            r#"SELECT t.channel_id, e.kind AS "kind: TicketEventKind",
+                e.actor_id, e.target_id, e.payload, e.created_at
+            FROM ticket_events e JOIN tickets t ON t.id = e.ticket_id
+            WHERE t.guild_id = ?1 AND (e.actor_id = ?2 OR e.target_id = ?2)
+            ORDER BY e.id DESC LIMIT ?3 OFFSET ?4"#,
+            guild_id,
+            user_id,
+            limit,
+            offset
+        )
+        .fetch_all(&*self.db)
+        .await
+    }
+
+    pub async fn count_user_events(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+    ) -> Result<i64, sqlx::Error> {
+        let guild_id = guild_id.get() as i64;
+        let user_id = user_id.get() as i64;
+        sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count: i64" FROM ticket_events e JOIN tickets t ON t.id = e.ticket_id
+            WHERE t.guild_id = ?1 AND (e.actor_id = ?2 OR e.target_id = ?2)"#,
+            guild_id,
+            user_id
+        )
+        .fetch_one(&*self.db)
+        .await
+    }
+
+    /// Stores a copy of `transcript` and points the ticket record at it.
+    pub async fn save_transcript(
+        &self,
+        ticket: &Ticket,
+        transcript: &Transcript,
+    ) -> Result<i64, sqlx::Error> {
+        let now = Timestamp::now().unix_timestamp();
+        let message_count = transcript.message_count as i64;
+        let mut tx = self.db.begin().await?;
+
+        let transcript_id = sqlx::query_scalar!(
+            "INSERT INTO ticket_transcripts (ticket_id, created_at, message_count, html, text)
+            VALUES (?, ?, ?, ?, ?) RETURNING id",
+            ticket.id,
+            now,
+            message_count,
+            transcript.html,
+            transcript.text
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            "UPDATE tickets SET transcript_id = ? WHERE id = ?",
+            transcript_id,
+            ticket.id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(transcript_id)
+    }
+
+    /// Stores the intake form answers the opener gave, as `(question, answer)`
+    /// pairs in the order they were asked.
+    pub async fn save_answers(
+        &self,
+        ticket: &Ticket,
+        answers: &[(String, String)],
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.db.begin().await?;
+        for (position, (question, answer)) in answers.iter().enumerate() {
+            let position = position as i64;
+            sqlx::query!(
+                "INSERT INTO ticket_answers (ticket_id, position, question, answer)
+                VALUES (?, ?, ?, ?)",
+                ticket.id,
+                position,
+                question,
+                answer
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await
+    }
+}
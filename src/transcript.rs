@@ -0,0 +1,210 @@
+use serenity::{all::*, prelude::SerenityError};
+use std::fmt::Write;
+
+pub struct Transcript {
+    pub html: String,
+    pub text: String,
+    pub message_count: usize,
+}
+
+/// Pages backwards through the whole history of `channel_id` and returns the
+/// messages oldest first.
+async fn fetch_history(
+    http: &impl CacheHttp,
+    channel_id: ChannelId,
+) -> Result<Vec<Message>, SerenityError> {
+    let mut messages = Vec::new();
+    let mut before = None;
+
+    loop {
+        let mut request = GetMessages::new().limit(100);
+        if let Some(id) = before {
+            request = request.before(id);
+        }
+
+        let page = channel_id.messages(http, request).await?;
+        let Some(oldest) = page.last() else {
+            break;
+        };
+        before = Some(oldest.id);
+
+        let done = page.len() < 100;
+        messages.extend(page);
+        if done {
+            break;
+        }
+    }
+
+    messages.reverse();
+    Ok(messages)
+}
+
+pub async fn build(
+    http: &impl CacheHttp,
+    channel: &GuildChannel,
+) -> Result<Transcript, SerenityError> {
+    let messages = fetch_history(http, channel.id).await?;
+
+    Ok(Transcript {
+        html: render_html(channel, &messages),
+        text: render_text(channel, &messages),
+        message_count: messages.len(),
+    })
+}
+
+fn render_text(channel: &GuildChannel, messages: &[Message]) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "Transcript of #{} ({})", channel.name, channel.id);
+    let _ = writeln!(out, "Generated at {}", Timestamp::now());
+    let _ = writeln!(out, "{} messages", messages.len());
+
+    for message in messages {
+        let _ = write!(
+            out,
+            "\n[{}] {} ({})",
+            message.timestamp, message.author.name, message.author.id
+        );
+        if let Some(edited) = message.edited_timestamp {
+            let _ = write!(out, " (edited {})", edited);
+        }
+        out.push('\n');
+
+        if !message.content.is_empty() {
+            let _ = writeln!(out, "{}", message.content);
+        }
+
+        for embed in &message.embeds {
+            let _ = writeln!(out, "[embed]");
+            if let Some(title) = &embed.title {
+                let _ = writeln!(out, "  {}", title);
+            }
+            if let Some(description) = &embed.description {
+                let _ = writeln!(out, "  {}", description.replace('\n', "\n  "));
+            }
+            for field in &embed.fields {
+                let _ = writeln!(out, "  {}: {}", field.name, field.value);
+            }
+            if let Some(footer) = &embed.footer {
+                let _ = writeln!(out, "  {}", footer.text);
+            }
+        }
+
+        for attachment in &message.attachments {
+            let _ = writeln!(
+                out,
+                "[attachment] {} {}",
+                attachment.filename, attachment.url
+            );
+        }
+    }
+
+    out
+}
+
+fn escape(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+const STYLE: &str = "body{background:#313338;color:#dbdee1;font-family:sans-serif;margin:0;padding:16px}\
+h1{font-size:18px;margin:0 0 4px}\
+.meta{color:#949ba4;font-size:12px;margin-bottom:16px}\
+.message{padding:6px 0;border-top:1px solid #3f4147}\
+.author{font-weight:bold;color:#f2f3f5}\
+.time{color:#949ba4;font-size:12px;margin-left:6px}\
+.content{white-space:pre-wrap;margin-top:2px}\
+.embed{border-left:4px solid #1e1f22;background:#2b2d31;padding:6px 10px;margin-top:4px;border-radius:4px}\
+.embed .title{font-weight:bold}\
+.embed .field{margin-top:4px}\
+.embed .footer{color:#949ba4;font-size:12px;margin-top:4px}\
+.attachment a{color:#00a8fc}";
+
+fn render_html(channel: &GuildChannel, messages: &[Message]) -> String {
+    let mut out = String::new();
+    let _ = write!(
+        out,
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>#{name}</title>\
+        <style>{STYLE}</style></head><body><h1>#{name}</h1>\
+        <div class=\"meta\">Channel {id} &middot; {count} messages &middot; generated {now}</div>",
+        name = escape(&channel.name),
+        id = channel.id,
+        count = messages.len(),
+        now = Timestamp::now(),
+    );
+
+    for message in messages {
+        let _ = write!(
+            out,
+            "<div class=\"message\"><span class=\"author\" title=\"{}\">{}</span>\
+            <span class=\"time\">{}</span>",
+            message.author.id,
+            escape(&message.author.name),
+            message.timestamp
+        );
+        if let Some(edited) = message.edited_timestamp {
+            let _ = write!(out, "<span class=\"time\">(edited {})</span>", edited);
+        }
+
+        if !message.content.is_empty() {
+            let _ = write!(
+                out,
+                "<div class=\"content\">{}</div>",
+                escape(&message.content)
+            );
+        }
+
+        for embed in &message.embeds {
+            let colour = embed
+                .colour
+                .map(|c| c.hex())
+                .unwrap_or_else(|| "1e1f22".to_string());
+            let _ = write!(
+                out,
+                "<div class=\"embed\" style=\"border-color:#{}\">",
+                colour
+            );
+            if let Some(title) = &embed.title {
+                let _ = write!(out, "<div class=\"title\">{}</div>", escape(title));
+            }
+            if let Some(description) = &embed.description {
+                let _ = write!(out, "<div class=\"content\">{}</div>", escape(description));
+            }
+            for field in &embed.fields {
+                let _ = write!(
+                    out,
+                    "<div class=\"field\"><b>{}</b><div class=\"content\">{}</div></div>",
+                    escape(&field.name),
+                    escape(&field.value)
+                );
+            }
+            if let Some(footer) = &embed.footer {
+                let _ = write!(out, "<div class=\"footer\">{}</div>", escape(&footer.text));
+            }
+            out.push_str("</div>");
+        }
+
+        for attachment in &message.attachments {
+            let _ = write!(
+                out,
+                "<div class=\"attachment\"><a href=\"{url}\">{name}</a></div>",
+                url = escape(&attachment.url),
+                name = escape(&attachment.filename)
+            );
+        }
+
+        out.push_str("</div>");
+    }
+
+    out.push_str("</body></html>");
+    out
+}